@@ -1,6 +1,8 @@
+#[cfg(feature = "bindgen")]
 extern crate bindgen;
 use std::env;
 use std::fs;
+#[cfg(feature = "bindgen")]
 use std::path::PathBuf;
 pub fn get_libpath() -> String {
     let mut libpath = "";
@@ -30,14 +32,20 @@ pub fn get_libpath() -> String {
     if cfg!(target_os = "windows") {
         libpath = "windows/x86";
     };
-    return libpath.to_string();
+    libpath.to_string()
 }
 
 fn set_dylib(archpath: String) {
     let libpath = "./libsvb/vendor/".to_string() + &archpath;
-    let path = std::fs::canonicalize(&libpath);
+    let path = match std::fs::canonicalize(&libpath) {
+        Ok(path) => path,
+        Err(e) => panic!(
+            "SVBCameraSDK not found in {} ({}), build with the `dynamic` feature to load it at runtime",
+            libpath, e
+        ),
+    };
     // Tell cargo to look for shared libraries in the specified directory
-    println!("cargo:rustc-link-search={}", path.unwrap().display());
+    println!("cargo:rustc-link-search={}", path.display());
     //println!("cargo:rustc-link-arg=-Wl,-rpath,{}", libpath);
 
     println!("cargo:rustc-env=DYLD_LIBRARY_PATH={}", libpath);
@@ -45,11 +53,23 @@ fn set_dylib(archpath: String) {
     // Tell cargo to tell rustc to link the system bzip2
     // shared library.
     println!("cargo:rustc-link-lib=SVBCameraSDK");
+}
+
+/// Let the runtime loader look into the vendored SDK directory when it exists.
+fn set_vendor_dir(archpath: String) {
+    let libpath = "./libsvb/vendor/".to_string() + &archpath;
+    if let Ok(path) = fs::canonicalize(&libpath) {
+        println!("cargo:rustc-env=SVB_SDK_VENDOR_DIR={}", path.display());
+    }
+}
 
+#[cfg(feature = "bindgen")]
+fn generate_bindings() {
     // Tell cargo to invalidate the built crate whenever the wrapper changes
-    println!("cargo:rerun-if-changed=./libsvb/include/SVBCameraSK.h");
+    println!("cargo:rerun-if-changed=./libsvb/include/SVBCameraSDK.h");
     let bindings = bindgen::Builder::default()
         .header("./libsvb/include/SVBCameraSDK.h")
+        .layout_tests(false)
         .parse_callbacks(Box::new(bindgen::CargoCallbacks))
         .generate()
         .expect("Unable to generate bindings");
@@ -63,7 +83,7 @@ fn create_dir() {
     let directory_name = "output"; // 作成するディレクトリ名
 
     // ディレクトリが存在しない場合に作成
-    if !fs::metadata(directory_name).is_ok() {
+    if fs::metadata(directory_name).is_err() {
         match fs::create_dir(directory_name) {
             Ok(_) => println!("Created directory: {}", directory_name),
             Err(e) => eprintln!("Error creating directory: {}", e),
@@ -74,6 +94,13 @@ fn create_dir() {
 }
 
 fn main() {
-    set_dylib(get_libpath());
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_DYNAMIC").is_some() {
+        set_vendor_dir(get_libpath());
+    } else {
+        set_dylib(get_libpath());
+    }
+    #[cfg(feature = "bindgen")]
+    generate_bindings();
     create_dir();
 }
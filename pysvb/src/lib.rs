@@ -3,13 +3,21 @@
 #![allow(non_snake_case)]
 use bayer;
 use pyo3::prelude::*;
-use svbony_camera_rs::camera::Camera;
+use svbony_camera_rs::camera::{self, Camera, CameraInfo, Closed, DetachedCapture};
 use svbony_camera_rs::debayer;
-use svbony_camera_rs::libsvb::{self, ROIFormat};
+use svbony_camera_rs::libsvb::{self, ControlType, ImageType, ROIFormat, SVBError};
+
+#[pyfunction]
+fn list_cameras() -> PyResult<Vec<SVBCameraInfo>> {
+    camera::list_cameras()
+        .map(|cameras| cameras.into_iter().map(SVBCameraInfo::from).collect())
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
+}
 
 #[pyfunction]
 fn get_num_of_camera() -> PyResult<i32> {
-    Ok(libsvb::_get_num_of_connected_cameras())
+    libsvb::_get_num_of_connected_cameras()
+        .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
 }
 #[pyclass]
 #[derive(Debug, Clone)]
@@ -155,6 +163,18 @@ struct SVBCameraInfo {
     camera_id: i32,
 }
 
+impl From<CameraInfo> for SVBCameraInfo {
+    fn from(info: CameraInfo) -> Self {
+        SVBCameraInfo {
+            friendly_name: info.name,
+            camera_sn: info.serial_number,
+            port_type: info.port_type,
+            device_id: info.device_id,
+            camera_id: info.camera_id,
+        }
+    }
+}
+
 #[pymethods]
 impl SVBCameraInfo {
     #[getter]
@@ -239,26 +259,82 @@ impl SVBCameraProperty {
 }
 #[pyclass]
 pub struct SVBCamera {
-    pub inner: Camera,
+    // Python drives start/stop across calls, so the capture can't be a
+    // `CaptureSession` borrowing `inner`. Declared first so it is stopped
+    // before the camera is closed.
+    capture: Option<DetachedCapture>,
+    // exactly one of `closed` and `opened` is set
+    closed: Option<Camera<Closed>>,
+    opened: Option<Camera>,
+}
+
+impl SVBCamera {
+    fn inner(&self) -> PyResult<&Camera> {
+        self.opened
+            .as_ref()
+            .ok_or_else(|| pyo3::exceptions::PyRuntimeError::new_err("camera is not initialized"))
+    }
+    fn inner_mut(&mut self) -> PyResult<&mut Camera> {
+        self.opened
+            .as_mut()
+            .ok_or_else(|| pyo3::exceptions::PyRuntimeError::new_err("camera is not initialized"))
+    }
+    /// ROI and image type are fixed while capturing.
+    fn check_not_capturing(&self) -> PyResult<()> {
+        match self.capture {
+            Some(_) => Err(pyo3::exceptions::PyRuntimeError::new_err(SVBError::VideoModeActive.to_string())),
+            None => Ok(()),
+        }
+    }
 }
 #[pymethods]
 impl SVBCamera {
     #[new]
     fn new(camera_idx: i32) -> PyResult<SVBCamera> {
-        let mut camera = Camera::new(camera_idx);
-        Ok(SVBCamera { inner: camera })
+        let camera = Camera::new(camera_idx);
+        Ok(SVBCamera { capture: None, closed: Some(camera), opened: None })
     }
-    fn init(&mut self) {
-        self.inner.init();
+    fn init(&mut self) -> PyResult<()> {
+        let camera = match self.closed.take() {
+            Some(camera) => camera,
+            None => return Ok(()),
+        };
+        let idx = camera.idx;
+        match camera.init() {
+            Ok(camera) => {
+                self.opened = Some(camera);
+                Ok(())
+            }
+            Err(e) => {
+                self.closed = Some(Camera::new(idx));
+                Err(pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
+            }
+        }
     }
-    fn close(&self) {
-        self.inner.close().unwrap();
+    fn close(&mut self) -> PyResult<()> {
+        self.stop_video_capture()?;
+        let camera = match self.opened.take() {
+            Some(camera) => camera,
+            None => return Ok(()),
+        };
+        let idx = camera.idx;
+        match camera.close() {
+            Ok(camera) => {
+                self.closed = Some(camera);
+                Ok(())
+            }
+            Err(e) => {
+                self.closed = Some(Camera::new(idx));
+                Err(pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
+            }
+        }
     }
     fn set_roi_format(&mut self, startx: i32, starty: i32, width: i32, height: i32, bin: i32) -> PyResult<()> {
-        match self.inner
+        self.check_not_capturing()?;
+        match self.inner_mut()?
             .set_roi_format(startx, starty, width, height, bin){
                 Ok(()) => {
-                    self.inner.roi = ROIFormat {
+                    self.inner_mut()?.roi = ROIFormat {
                         startx,
                         starty,
                         width,
@@ -272,11 +348,12 @@ impl SVBCamera {
   
     }
     fn set_resolution(&mut self,width: i32,height: i32) -> PyResult<()>{
-        let roi = self.inner.roi;
-        match self.inner
+        self.check_not_capturing()?;
+        let roi = self.inner()?.roi;
+        match self.inner_mut()?
             .set_roi_format(roi.startx, roi.starty, width, height, roi.bin){
                 Ok(()) =>{ 
-                    self.inner.roi = ROIFormat {
+                    self.inner_mut()?.roi = ROIFormat {
                         startx : roi.startx,
                         starty : roi.starty,
                         width,
@@ -290,25 +367,26 @@ impl SVBCamera {
             }
 
     }
-    fn set_bin(&mut self,bin : i32){
-        let roi = self.inner.roi;
-        self.inner
+    fn set_bin(&mut self,bin : i32) -> PyResult<()> {
+        self.check_not_capturing()?;
+        let roi = self.inner()?.roi;
+        self.inner_mut()?
             .set_roi_format(roi.startx, roi.starty, roi.width, roi.height, bin)
-            .unwrap();
-        self.inner.roi = ROIFormat {
+            .map_err(|e| pyo3::exceptions::PyBufferError::new_err(e.to_string()))?;
+        self.inner_mut()?.roi = ROIFormat {
             startx : roi.startx,
             starty : roi.starty,
             width : roi.width,
             height: roi.height,
             bin 
         };
-
+        Ok(())
     }
     fn get_num_of_controls(&self,) -> PyResult<i32> {
-        Ok(self.inner.get_num_of_controls().unwrap())
+        self.inner()?.get_num_of_controls().map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
     }
     fn get_ctl_caps(&self, ctl_idx : i32) -> PyResult<SVBControlCaps>{
-        let caps = self.inner.get_ctl_caps_by_idx(ctl_idx).unwrap();
+        let caps = self.inner()?.get_ctl_caps_by_idx(ctl_idx).map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
         let a: Vec<u8> = caps.Name.iter().map(|&x| x as u8).collect();
         let b: Vec<u8> = caps.Description.iter().map(|&x| x as u8).collect();
         Ok(SVBControlCaps {
@@ -326,7 +404,7 @@ impl SVBCamera {
 
     }
     fn get_info(&self) -> PyResult<SVBCameraInfo> {
-        let info = self.inner.info;
+        let info = self.inner()?.info;
         let a: Vec<u8> = info.FriendlyName.iter().map(|&x| x as u8).collect();
         let b: Vec<u8> = info.CameraSN.iter().map(|&x| x as u8).collect();
         let c: Vec<u8> = info.PortType.iter().map(|&x| x as u8).collect();
@@ -339,7 +417,7 @@ impl SVBCamera {
         })
     }
     fn get_prop(&self) -> PyResult<SVBCameraProperty> {
-        let prop = self.inner.prop;
+        let prop = self.inner()?.prop;
         Ok(SVBCameraProperty {
             max_height: prop.MaxHeight,
             max_width: prop.MaxWidth,
@@ -353,7 +431,7 @@ impl SVBCamera {
     }
 
     fn get_roi_format(&self) -> PyResult<SVBROIFormat> {
-        let roi = self.inner.get_roi_format().unwrap();
+        let roi = self.inner()?.get_roi_format().map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
         Ok(SVBROIFormat {
             startx: roi.startx,
             starty: roi.starty,
@@ -362,58 +440,89 @@ impl SVBCamera {
             bin: roi.bin,
         })
     }
-    fn set_ctl_value(&self, ctl_type: libsvb::SVB_CONTROL_TYPE, value: i64, is_auto: u32) {
+    fn set_ctl_value(&self, ctl_type: libsvb::SVB_CONTROL_TYPE, value: i64, is_auto: u32) -> PyResult<()> {
+        let ctl_type = ControlType::try_from(ctl_type)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
         match self
-            .inner
+            .inner()?
             .set_ctl_value(ctl_type, value, is_auto as libsvb::SVB_BOOL)
         {
-            Ok(()) => (),
-            Err(e) => panic!("{}", e),
+            Ok(()) => Ok(()),
+            Err(e) => Err(pyo3::exceptions::PyRuntimeError::new_err(e.to_string())),
         }
     }
     fn get_ctl_value(&self, ctl_type: libsvb::SVB_CONTROL_TYPE) -> PyResult<Vec<i64>> {
-        let state = self.inner.get_ctl_value(ctl_type).unwrap();
+        let ctl_type = ControlType::try_from(ctl_type)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        let state = self.inner()?.get_ctl_value(ctl_type).map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
         Ok(vec![state.value, state.is_auto as i64])
     }
-    fn get_property(&self) {
-        let prop = libsvb::_get_camera_prop(self.inner.id);
+    fn get_property(&self) -> PyResult<()> {
+        let prop = libsvb::_get_camera_prop(self.inner()?.id);
+        Ok(())
     }
-    fn start_video_capture(&self) {
-        self.inner.start_video_capture().unwrap();
+    fn start_video_capture(&mut self) -> PyResult<()> {
+        self.check_not_capturing()?;
+        let capture = self
+            .inner()?
+            .start_detached_capture()
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+        self.capture = Some(capture);
+        Ok(())
     }
     pub fn get_raw_frame(&self, ) -> PyResult<Vec<u8>> {
-        match self.inner.get_video_frame() {
-            Ok(buf) => Ok(buf),
+        let capture = self
+            .capture
+            .as_ref()
+            .ok_or_else(|| pyo3::exceptions::PyRuntimeError::new_err("video capture is not started"))?;
+        match capture.get_video_frame() {
+            // copied out, so the frame buffer goes back to the pool
+            Ok(frame) => Ok(frame.data.to_vec()),
             Err(e) => Err(pyo3::exceptions::PyBufferError::new_err(e.to_string())),
         }
     }
 
-    fn stop_video_capture(&self) {
-        self.inner.stop_video_capture().unwrap();
+    fn get_dropped_frames(&self) -> PyResult<i32> {
+        self.inner()?
+            .dropped_frames()
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
+    }
+
+    fn stop_video_capture(&mut self) -> PyResult<()> {
+        match self.capture.take() {
+            Some(capture) => capture.stop().map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string())),
+            None => Ok(()),
+        }
     }
     fn get_img_type(&self) -> PyResult<i32> {
-        Ok(self.inner.get_img_type().unwrap())
+        self.inner()?.get_img_type().map(i32::from).map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
     }
     fn get_bayer_pattern(&self) -> PyResult<u32> {
-        Ok(self.inner.prop.BayerPattern)
+        Ok(self.inner()?.prop.BayerPattern)
     }
-    fn set_img_type(&self, img_type: i32) {
-        self.inner.set_img_type(img_type).unwrap();
+    fn set_img_type(&mut self, img_type: i32) -> PyResult<()> {
+        self.check_not_capturing()?;
+        let img_type = ImageType::try_from(img_type)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        self.inner_mut()?.set_img_type(img_type).map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
     }
-    fn adjust_white_balance(&self) {
-        self.inner.adjust_white_blance().unwrap();
+    fn adjust_white_balance(&self) -> PyResult<()> {
+        self.inner()?
+            .adjust_white_blance()
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
     }
 }
 
 #[pyfunction]
 fn debayer_buffer(camera: &SVBCamera, buffer: Vec<u8>, alg: PyDemosaic) -> PyResult<Vec<u8>> {
-    let roi = camera.inner.roi;
+    let roi = camera.inner()?.roi;
     let width = roi.width as u32;
     let height = roi.height as u32;
-    let img_type = camera.inner.get_img_type().unwrap();
-    let bayer_idx = camera.inner.get_bayer_pattern();
-
-    let bayer_pattern = debayer::cfa_from_u32(bayer_idx);
+    let img_type = camera.inner()?.get_img_type().map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+    let bayer_pattern = camera
+        .inner()?
+        .get_bayer_pattern()
+        .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
     let runtime = debayer::Debayer::new(width, height, bayer_pattern);
 
     let alg = match alg {
@@ -424,23 +533,24 @@ fn debayer_buffer(camera: &SVBCamera, buffer: Vec<u8>, alg: PyDemosaic) -> PyRes
     };
     // convert to image by image type (RAW8,RAW16,RGB24,Y8)
     let debayer_buf = match img_type {
-        libsvb::SVB_IMG_TYPE_SVB_IMG_RAW8 => {
-            runtime.run_from_buf(buffer, debayer::Depth::Depth8, alg)
+        ImageType::Raw8 => {
+            runtime.run_from_buf(&buffer, debayer::Depth::Depth8, alg)
         }
 
-        libsvb::SVB_IMG_TYPE_SVB_IMG_RAW16 => {
-            runtime.run_from_buf(buffer, debayer::Depth::Depth16LE, alg)
+        ImageType::Raw16 => {
+            runtime.run_from_buf(&buffer, debayer::Depth::Depth16LE, alg)
         }
 
         _ => Err(bayer::BayerError::WrongDepth),
     };
-    Ok(debayer_buf.unwrap())
+    debayer_buf.map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
 
 }
 /// A Python module implemented in Rust.
 #[pymodule]
 fn _lowlevel(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(get_num_of_camera, m)?)?;
+    m.add_function(wrap_pyfunction!(list_cameras, m)?)?;
     m.add_function(wrap_pyfunction!(debayer_buffer, m)?)?;
     m.add_class::<SVBCamera>()?;
     m.add_class::<SVBROIFormat>()?;
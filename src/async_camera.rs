@@ -0,0 +1,357 @@
+use crate::camera::Camera;
+use crate::frame::Frame;
+use crate::libsvb::{ControlType, ControlTypeState, ImageType, ROIFormat, SVBControlValue, SVBError};
+use crate::stream::{FrameStream, StreamOptions};
+
+use futures_core::Stream;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc as async_mpsc, oneshot};
+
+/// How long the worker waits for a frame before serving queued calls again.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+type ControlCall = Box<dyn FnOnce(&Camera) + Send>;
+/// Gets `None` while the camera is capturing.
+type ConfigureCall = Box<dyn FnOnce(Option<&mut Camera>) + Send>;
+
+enum Command {
+    Control(ControlCall),
+    Configure(ConfigureCall),
+    Stream {
+        options: StreamOptions,
+        frames: async_mpsc::Sender<Result<Frame, SVBError>>,
+        started: oneshot::Sender<Result<(), SVBError>>,
+    },
+}
+
+/// Async handle to an opened camera.
+///
+/// The camera lives on a dedicated thread which makes every blocking SDK
+/// call, so awaiting a call never blocks the runtime. Calls are served in
+/// order; while a stream is running they are served between frames, so they
+/// can take up to `POLL_INTERVAL` longer.
+#[derive(Debug)]
+pub struct AsyncCamera {
+    commands: Sender<Command>,
+    camera: oneshot::Receiver<Camera>,
+}
+
+impl AsyncCamera {
+    /// Move `camera` onto its own thread.
+    pub fn new(camera: Camera) -> Result<Self, SVBError> {
+        let (commands, rx) = mpsc::channel();
+        let (done, camera_rx) = oneshot::channel();
+        thread::Builder::new()
+            .name(format!("svb-camera-{}", camera.id))
+            .spawn(move || {
+                let camera = worker(camera, rx);
+                // nobody waiting for the camera means it's closed by dropping it here
+                let _ = done.send(camera);
+            })
+            .map_err(|e| {
+                error!("{} : Failed to spawn camera thread", e);
+                SVBError::GeneralError
+            })?;
+        Ok(Self {
+            commands,
+            camera: camera_rx,
+        })
+    }
+
+    /// Run `f` with the camera on its thread, also while a stream is running.
+    pub async fn run<T, F>(&self, f: F) -> Result<T, SVBError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Camera) -> Result<T, SVBError> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.send(Command::Control(Box::new(move |camera| {
+            let _ = tx.send(f(camera));
+        })))?;
+        rx.await.map_err(|_| SVBError::CameraClosed)?
+    }
+
+    /// Run `f` with mutable access to the camera, e.g. to change the ROI.
+    /// Fails with `SVBError::VideoModeActive` while a stream is running.
+    pub async fn configure<T, F>(&self, f: F) -> Result<T, SVBError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Camera) -> Result<T, SVBError> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.send(Command::Configure(Box::new(move |camera| {
+            let _ = tx.send(match camera {
+                Some(camera) => f(camera),
+                None => Err(SVBError::VideoModeActive),
+            });
+        })))?;
+        rx.await.map_err(|_| SVBError::CameraClosed)?
+    }
+
+    pub async fn get_ctl_value(&self, ctl_type: ControlType) -> Result<ControlTypeState, SVBError> {
+        self.run(move |camera| camera.get_ctl_value(ctl_type)).await
+    }
+
+    pub async fn set_ctl_value(
+        &self,
+        ctl_type: ControlType,
+        value: SVBControlValue,
+        is_auto: u32,
+    ) -> Result<(), SVBError> {
+        self.run(move |camera| camera.set_ctl_value(ctl_type, value, is_auto))
+            .await
+    }
+
+    pub async fn get_roi_format(&self) -> Result<ROIFormat, SVBError> {
+        self.run(|camera| camera.get_roi_format()).await
+    }
+
+    pub async fn set_roi_format(
+        &self,
+        startx: i32,
+        starty: i32,
+        width: i32,
+        height: i32,
+        bin: i32,
+    ) -> Result<(), SVBError> {
+        self.configure(move |camera| camera.set_roi_format(startx, starty, width, height, bin))
+            .await
+    }
+
+    pub async fn get_img_type(&self) -> Result<ImageType, SVBError> {
+        self.run(|camera| camera.get_img_type()).await
+    }
+
+    pub async fn set_img_type(&self, img_type: ImageType) -> Result<(), SVBError> {
+        self.configure(move |camera| camera.set_img_type(img_type)).await
+    }
+
+    /// Start video capture as with `Camera::start_stream`. Dropping the
+    /// returned stream stops the capture.
+    pub async fn frames(&self, options: StreamOptions) -> Result<AsyncFrameStream, SVBError> {
+        let (frames, rx) = async_mpsc::channel(options.queue_size.max(1));
+        let (started, started_rx) = oneshot::channel();
+        self.send(Command::Stream {
+            options,
+            frames,
+            started,
+        })?;
+        started_rx.await.map_err(|_| SVBError::CameraClosed)??;
+        Ok(AsyncFrameStream { frames: rx })
+    }
+
+    /// Stop the camera thread and get the camera back.
+    pub async fn into_inner(self) -> Result<Camera, SVBError> {
+        drop(self.commands);
+        self.camera.await.map_err(|_| SVBError::CameraClosed)
+    }
+
+    fn send(&self, command: Command) -> Result<(), SVBError> {
+        self.commands
+            .send(command)
+            .map_err(|_| SVBError::CameraClosed)
+    }
+}
+
+/// Frames of a capture started by `AsyncCamera::frames`.
+///
+/// Dropping the stream cancels it; the capture is stopped before the camera
+/// serves the next call.
+#[derive(Debug)]
+pub struct AsyncFrameStream {
+    frames: async_mpsc::Receiver<Result<Frame, SVBError>>,
+}
+
+impl AsyncFrameStream {
+    /// The next frame, `None` once the capture has ended.
+    pub async fn next_frame(&mut self) -> Option<Result<Frame, SVBError>> {
+        self.frames.recv().await
+    }
+}
+
+impl Stream for AsyncFrameStream {
+    type Item = Result<Frame, SVBError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.frames.poll_recv(cx)
+    }
+}
+
+fn worker(mut camera: Camera, commands: Receiver<Command>) -> Camera {
+    let mut deferred = None;
+    loop {
+        let command = match deferred.take() {
+            Some(command) => command,
+            None => match commands.recv() {
+                Ok(command) => command,
+                Err(_) => break,
+            },
+        };
+        match command {
+            Command::Control(f) => f(&camera),
+            Command::Configure(f) => f(Some(&mut camera)),
+            Command::Stream {
+                options,
+                frames,
+                started,
+            } => {
+                let stream = match camera.start_stream(options) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        let _ = started.send(Err(e));
+                        continue;
+                    }
+                };
+                let _ = started.send(Ok(()));
+                match forward_frames(stream, &commands, frames) {
+                    StreamEnd::Ended => (),
+                    StreamEnd::Deferred(command) => deferred = Some(command),
+                    StreamEnd::Disconnected => break,
+                }
+            }
+        }
+    }
+    camera
+}
+
+enum StreamEnd {
+    Ended,
+    /// The stream was cancelled by a call which needs the capture stopped.
+    Deferred(Command),
+    /// The `AsyncCamera` has been dropped.
+    Disconnected,
+}
+
+/// Pass the frames of `stream` on to `frames` and serve `commands` meanwhile,
+/// until the receiver of `frames` is dropped or the stream ends.
+fn forward_frames(
+    mut stream: FrameStream<'_>,
+    commands: &Receiver<Command>,
+    frames: async_mpsc::Sender<Result<Frame, SVBError>>,
+) -> StreamEnd {
+    let mut pending = None;
+    loop {
+        loop {
+            let command = match commands.try_recv() {
+                Ok(command) => command,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return StreamEnd::Disconnected,
+            };
+            match command {
+                Command::Control(f) => f(&stream),
+                // a call made after dropping the frame stream must see the capture stopped
+                command if frames.is_closed() => {
+                    info!("Frame stream of camera_id {} cancelled", stream.id);
+                    return StreamEnd::Deferred(command);
+                }
+                Command::Configure(f) => f(None),
+                Command::Stream { started, .. } => {
+                    let _ = started.send(Err(SVBError::VideoModeActive));
+                }
+            }
+        }
+        if frames.is_closed() {
+            info!("Frame stream of camera_id {} cancelled", stream.id);
+            return StreamEnd::Ended;
+        }
+        match pending.take() {
+            // frames queue up in `stream` while the receiver is behind
+            Some(item) => match frames.try_send(item) {
+                Ok(()) => (),
+                Err(TrySendError::Full(item)) => {
+                    pending = Some(item);
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(TrySendError::Closed(_)) => (),
+            },
+            None => {
+                pending = stream.next_timeout(POLL_INTERVAL);
+                if pending.is_none() && stream.is_finished() {
+                    return StreamEnd::Ended;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AsyncCamera;
+    use crate::camera::Camera;
+    use crate::libsvb::{ControlType, SVBError};
+    use crate::simulator::{SimulatedBackend, SimulatedCameraConfig};
+    use crate::stream::StreamOptions;
+
+    use futures_core::Stream;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn camera() -> Camera {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            num_stars: 20,
+            ..Default::default()
+        }]));
+        Camera::with_backend(0, backend).init().unwrap()
+    }
+
+    #[test]
+    fn test_frames_and_controls() {
+        let camera = camera();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let camera = AsyncCamera::new(camera).unwrap();
+            camera.set_roi_format(0, 0, 160, 120, 1).await.unwrap();
+            camera.set_ctl_value(ControlType::Exposure, 1000, 0).await.unwrap();
+
+            let mut frames = camera.frames(StreamOptions::default()).await.unwrap();
+            let frame = frames.next_frame().await.unwrap().unwrap();
+            assert_eq!(frame.data.len(), 160 * 120);
+
+            // controls can be changed while streaming, the ROI can't
+            camera.set_ctl_value(ControlType::Gain, 50, 0).await.unwrap();
+            assert!(matches!(
+                camera.set_roi_format(0, 0, 320, 240, 1).await,
+                Err(SVBError::VideoModeActive)
+            ));
+            let gain = loop {
+                let frame = poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)).await;
+                let frame = frame.unwrap().unwrap();
+                if frame.gain == 50 {
+                    break frame.gain;
+                }
+            };
+            assert_eq!(gain, 50);
+
+            // cancelling the stream stops the capture
+            drop(frames);
+            camera.set_roi_format(0, 0, 320, 240, 1).await.unwrap();
+            let camera = camera.into_inner().await.unwrap();
+            assert_eq!(camera.roi.width, 320);
+        });
+    }
+
+    #[test]
+    fn test_cancel_during_exposure() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        runtime.block_on(async {
+            let camera = AsyncCamera::new(camera()).unwrap();
+            camera.set_ctl_value(ControlType::Exposure, 300_000_000, 0).await.unwrap();
+            let frames = camera.frames(StreamOptions::default()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            // the capture is stopped without waiting for the exposure to end
+            drop(frames);
+            let set_roi = camera.set_roi_format(0, 0, 160, 120, 1);
+            tokio::time::timeout(Duration::from_secs(1), set_roi).await.unwrap().unwrap();
+        });
+    }
+}
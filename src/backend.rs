@@ -0,0 +1,280 @@
+use crate::libsvb::{self, ControlTypeState, ROIFormat, SVBError};
+use std::fmt;
+
+/// The SDK surface used by `Camera`.
+///
+/// `SdkBackend` forwards every call to the SVBCameraSDK through `libsvb`,
+/// `simulator::SimulatedBackend` implements the same calls in-process so the
+/// capture path can run without a physical camera.
+pub trait Backend: fmt::Debug + Send + Sync {
+    fn get_num_of_connected_cameras(&self) -> Result<i32, SVBError>;
+
+    fn get_camera_info(&self, camera_idx: i32) -> Result<libsvb::SVB_CAMERA_INFO, SVBError>;
+
+    fn get_camera_prop(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_PROPERTY, SVBError>;
+
+    fn get_camera_prop_ex(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_PROPERTY_EX, SVBError>;
+
+    fn get_firmware_version(&self, camera_id: i32) -> Result<String, SVBError>;
+
+    fn get_sdk_version(&self) -> Result<String, SVBError>;
+
+    /// Whether the firmware needs an upgrade, and the oldest version which doesn't.
+    fn is_camera_need_to_upgrade(&self, camera_id: i32) -> Result<(bool, String), SVBError>;
+
+    /// Sensor pixel size in µm.
+    fn get_sensor_pixel_size(&self, camera_id: i32) -> Result<f32, SVBError>;
+
+    fn open_camera(&self, camera_id: i32) -> Result<(), SVBError>;
+
+    fn close_camera(&self, camera_id: i32) -> Result<(), SVBError>;
+
+    fn get_num_of_controls(&self, camera_id: i32) -> Result<i32, SVBError>;
+
+    fn get_ctl_caps(&self, camera_id: i32, ctl_idx: i32)
+        -> Result<libsvb::SVB_CONTROL_CAPS, SVBError>;
+
+    fn get_ctl_value(
+        &self,
+        camera_id: i32,
+        ctl_type: libsvb::SVB_CONTROL_TYPE,
+    ) -> Result<ControlTypeState, SVBError>;
+
+    fn set_ctl_value(
+        &self,
+        camera_id: i32,
+        ctl_type: libsvb::SVB_CONTROL_TYPE,
+        value: libsvb::SVBControlValue,
+        is_auto: libsvb::SVB_BOOL,
+    ) -> Result<(), SVBError>;
+
+    fn get_roi_format(&self, camera_id: i32) -> Result<ROIFormat, SVBError>;
+
+    fn set_roi_format(&self, camera_id: i32, roi: ROIFormat) -> Result<(), SVBError>;
+
+    fn get_img_type(&self, camera_id: i32) -> Result<libsvb::SVB_IMG_TYPE, SVBError>;
+
+    fn set_img_type(&self, camera_id: i32, img_type: libsvb::SVB_IMG_TYPE) -> Result<(), SVBError>;
+
+    fn start_video_capture(&self, camera_id: i32) -> Result<(), SVBError>;
+
+    fn stop_video_capture(&self, camera_id: i32) -> Result<(), SVBError>;
+
+    /// Fill `buf` with one frame, blocking for at most `wait_ms` (-1 waits forever).
+    fn get_video_data(&self, camera_id: i32, buf: &mut [u8], wait_ms: i32)
+        -> Result<(), SVBError>;
+
+    fn get_serial_number(&self, camera_id: i32) -> Result<libsvb::SVB_SN, SVBError>;
+
+    fn get_dropped_frames(&self, camera_id: i32) -> Result<i32, SVBError>;
+
+    fn adjust_white_balance(&self, camera_id: i32) -> Result<(), SVBError>;
+
+    fn set_auto_save_param(&self, camera_id: i32, enable: bool) -> Result<(), SVBError>;
+
+    fn restore_default_param(&self, camera_id: i32) -> Result<(), SVBError>;
+
+    fn get_trigger_output_io_conf(
+        &self,
+        camera_id: i32,
+        pin: libsvb::SVB_TRIG_OUTPUT_PIN,
+    ) -> Result<libsvb::TriggerOutput, SVBError>;
+
+    fn set_trigger_output_io_conf(
+        &self,
+        camera_id: i32,
+        pin: libsvb::SVB_TRIG_OUTPUT_PIN,
+        output: libsvb::TriggerOutput,
+    ) -> Result<(), SVBError>;
+
+    fn get_camera_support_mode(&self, camera_id: i32) -> Result<libsvb::SVB_SUPPORTED_MODE, SVBError>;
+
+    fn can_pulse_guide(&self, camera_id: i32) -> Result<bool, SVBError>;
+
+    /// Send an ST-4 pulse of `duration` ms.
+    fn pulse_guide(&self, camera_id: i32, direction: libsvb::SVB_GUIDE_DIRECTION, duration: i32)
+        -> Result<(), SVBError>;
+
+    fn get_camera_mode(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_MODE, SVBError>;
+
+    fn set_camera_mode(&self, camera_id: i32, mode: libsvb::SVB_CAMERA_MODE) -> Result<(), SVBError>;
+
+    fn send_soft_trigger(&self, camera_id: i32) -> Result<(), SVBError>;
+}
+
+/// Backend calling into the SVBCameraSDK shared library.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SdkBackend;
+
+impl Backend for SdkBackend {
+    fn get_num_of_connected_cameras(&self) -> Result<i32, SVBError> {
+        libsvb::_get_num_of_connected_cameras()
+    }
+
+    fn get_camera_info(&self, camera_idx: i32) -> Result<libsvb::SVB_CAMERA_INFO, SVBError> {
+        libsvb::_get_camera_info(camera_idx)
+    }
+
+    fn get_camera_prop(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_PROPERTY, SVBError> {
+        libsvb::_get_camera_prop(camera_id)
+    }
+
+    fn get_camera_prop_ex(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_PROPERTY_EX, SVBError> {
+        libsvb::_get_camera_prop_ex(camera_id)
+    }
+
+    fn get_firmware_version(&self, camera_id: i32) -> Result<String, SVBError> {
+        libsvb::_get_camera_firmware_version(camera_id)
+    }
+
+    fn get_sdk_version(&self) -> Result<String, SVBError> {
+        libsvb::_get_sdk_version()
+    }
+
+    fn is_camera_need_to_upgrade(&self, camera_id: i32) -> Result<(bool, String), SVBError> {
+        libsvb::_is_camera_need_to_upgrade(camera_id)
+    }
+
+    fn get_sensor_pixel_size(&self, camera_id: i32) -> Result<f32, SVBError> {
+        libsvb::_get_sensor_pixel_size(camera_id)
+    }
+
+    fn open_camera(&self, camera_id: i32) -> Result<(), SVBError> {
+        libsvb::_open_camera(camera_id)
+    }
+
+    fn close_camera(&self, camera_id: i32) -> Result<(), SVBError> {
+        libsvb::_close_camera(camera_id)
+    }
+
+    fn get_num_of_controls(&self, camera_id: i32) -> Result<i32, SVBError> {
+        libsvb::_get_num_of_controls(camera_id)
+    }
+
+    fn get_ctl_caps(
+        &self,
+        camera_id: i32,
+        ctl_idx: i32,
+    ) -> Result<libsvb::SVB_CONTROL_CAPS, SVBError> {
+        libsvb::_get_ctl_caps(camera_id, ctl_idx)
+    }
+
+    fn get_ctl_value(
+        &self,
+        camera_id: i32,
+        ctl_type: libsvb::SVB_CONTROL_TYPE,
+    ) -> Result<ControlTypeState, SVBError> {
+        libsvb::_get_ctl_value(camera_id, ctl_type)
+    }
+
+    fn set_ctl_value(
+        &self,
+        camera_id: i32,
+        ctl_type: libsvb::SVB_CONTROL_TYPE,
+        value: libsvb::SVBControlValue,
+        is_auto: libsvb::SVB_BOOL,
+    ) -> Result<(), SVBError> {
+        libsvb::_set_ctl_value(camera_id, ctl_type, value, is_auto)
+    }
+
+    fn get_roi_format(&self, camera_id: i32) -> Result<ROIFormat, SVBError> {
+        libsvb::_get_roi_format(camera_id)
+    }
+
+    fn set_roi_format(&self, camera_id: i32, roi: ROIFormat) -> Result<(), SVBError> {
+        libsvb::_set_roi_format(
+            camera_id, roi.startx, roi.starty, roi.width, roi.height, roi.bin,
+        )
+    }
+
+    fn get_img_type(&self, camera_id: i32) -> Result<libsvb::SVB_IMG_TYPE, SVBError> {
+        libsvb::_get_img_type(camera_id)
+    }
+
+    fn set_img_type(&self, camera_id: i32, img_type: libsvb::SVB_IMG_TYPE) -> Result<(), SVBError> {
+        libsvb::_set_img_type(camera_id, img_type)
+    }
+
+    fn start_video_capture(&self, camera_id: i32) -> Result<(), SVBError> {
+        libsvb::_start_video_capture(camera_id)
+    }
+
+    fn stop_video_capture(&self, camera_id: i32) -> Result<(), SVBError> {
+        libsvb::_stop_video_capture(camera_id)
+    }
+
+    fn get_video_data(
+        &self,
+        camera_id: i32,
+        buf: &mut [u8],
+        wait_ms: i32,
+    ) -> Result<(), SVBError> {
+        libsvb::_get_video_data(camera_id, buf, wait_ms)
+    }
+
+    fn get_serial_number(&self, camera_id: i32) -> Result<libsvb::SVB_SN, SVBError> {
+        libsvb::_get_serial_number(camera_id)
+    }
+
+    fn get_dropped_frames(&self, camera_id: i32) -> Result<i32, SVBError> {
+        libsvb::_get_droped_frame(camera_id)
+    }
+
+    fn adjust_white_balance(&self, camera_id: i32) -> Result<(), SVBError> {
+        libsvb::_adjust_white_balance(camera_id)
+    }
+
+    fn set_auto_save_param(&self, camera_id: i32, enable: bool) -> Result<(), SVBError> {
+        libsvb::_set_auto_save_param(camera_id, enable)
+    }
+
+    fn restore_default_param(&self, camera_id: i32) -> Result<(), SVBError> {
+        libsvb::_restore_default_param(camera_id)
+    }
+
+    fn get_trigger_output_io_conf(
+        &self,
+        camera_id: i32,
+        pin: libsvb::SVB_TRIG_OUTPUT_PIN,
+    ) -> Result<libsvb::TriggerOutput, SVBError> {
+        libsvb::_get_trigger_output_io_conf(camera_id, pin)
+    }
+
+    fn set_trigger_output_io_conf(
+        &self,
+        camera_id: i32,
+        pin: libsvb::SVB_TRIG_OUTPUT_PIN,
+        output: libsvb::TriggerOutput,
+    ) -> Result<(), SVBError> {
+        libsvb::_set_trigger_output_io_conf(camera_id, pin, output)
+    }
+
+    fn get_camera_support_mode(&self, camera_id: i32) -> Result<libsvb::SVB_SUPPORTED_MODE, SVBError> {
+        libsvb::_get_camera_support_mode(camera_id)
+    }
+
+    fn can_pulse_guide(&self, camera_id: i32) -> Result<bool, SVBError> {
+        libsvb::_can_pulse_guide(camera_id)
+    }
+
+    fn pulse_guide(
+        &self,
+        camera_id: i32,
+        direction: libsvb::SVB_GUIDE_DIRECTION,
+        duration: i32,
+    ) -> Result<(), SVBError> {
+        libsvb::_pulse_guide(camera_id, direction, duration)
+    }
+
+    fn get_camera_mode(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_MODE, SVBError> {
+        libsvb::_get_camera_mode(camera_id)
+    }
+
+    fn set_camera_mode(&self, camera_id: i32, mode: libsvb::SVB_CAMERA_MODE) -> Result<(), SVBError> {
+        libsvb::_set_camera_mode(camera_id, mode)
+    }
+
+    fn send_soft_trigger(&self, camera_id: i32) -> Result<(), SVBError> {
+        libsvb::_send_soft_trigger(camera_id)
+    }
+}
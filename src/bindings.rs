@@ -0,0 +1,351 @@
+/* automatically generated by rust-bindgen 0.68.1 */
+
+pub const SVBCAMERA_ID_MAX: u32 = 128;
+pub const SVB_BAYER_PATTERN_SVB_BAYER_RG: SVB_BAYER_PATTERN = 0;
+pub const SVB_BAYER_PATTERN_SVB_BAYER_BG: SVB_BAYER_PATTERN = 1;
+pub const SVB_BAYER_PATTERN_SVB_BAYER_GR: SVB_BAYER_PATTERN = 2;
+pub const SVB_BAYER_PATTERN_SVB_BAYER_GB: SVB_BAYER_PATTERN = 3;
+pub type SVB_BAYER_PATTERN = ::std::os::raw::c_uint;
+pub const SVB_IMG_TYPE_SVB_IMG_RAW8: SVB_IMG_TYPE = 0;
+pub const SVB_IMG_TYPE_SVB_IMG_RAW10: SVB_IMG_TYPE = 1;
+pub const SVB_IMG_TYPE_SVB_IMG_RAW12: SVB_IMG_TYPE = 2;
+pub const SVB_IMG_TYPE_SVB_IMG_RAW14: SVB_IMG_TYPE = 3;
+pub const SVB_IMG_TYPE_SVB_IMG_RAW16: SVB_IMG_TYPE = 4;
+pub const SVB_IMG_TYPE_SVB_IMG_Y8: SVB_IMG_TYPE = 5;
+pub const SVB_IMG_TYPE_SVB_IMG_Y10: SVB_IMG_TYPE = 6;
+pub const SVB_IMG_TYPE_SVB_IMG_Y12: SVB_IMG_TYPE = 7;
+pub const SVB_IMG_TYPE_SVB_IMG_Y14: SVB_IMG_TYPE = 8;
+pub const SVB_IMG_TYPE_SVB_IMG_Y16: SVB_IMG_TYPE = 9;
+pub const SVB_IMG_TYPE_SVB_IMG_RGB24: SVB_IMG_TYPE = 10;
+pub const SVB_IMG_TYPE_SVB_IMG_RGB32: SVB_IMG_TYPE = 11;
+pub const SVB_IMG_TYPE_SVB_IMG_END: SVB_IMG_TYPE = -1;
+pub type SVB_IMG_TYPE = ::std::os::raw::c_int;
+pub const SVB_GUIDE_DIRECTION_SVB_GUIDE_NORTH: SVB_GUIDE_DIRECTION = 0;
+pub const SVB_GUIDE_DIRECTION_SVB_GUIDE_SOUTH: SVB_GUIDE_DIRECTION = 1;
+pub const SVB_GUIDE_DIRECTION_SVB_GUIDE_EAST: SVB_GUIDE_DIRECTION = 2;
+pub const SVB_GUIDE_DIRECTION_SVB_GUIDE_WEST: SVB_GUIDE_DIRECTION = 3;
+pub type SVB_GUIDE_DIRECTION = ::std::os::raw::c_uint;
+pub const SVB_FLIP_STATUS_SVB_FLIP_NONE: SVB_FLIP_STATUS = 0;
+pub const SVB_FLIP_STATUS_SVB_FLIP_HORIZ: SVB_FLIP_STATUS = 1;
+pub const SVB_FLIP_STATUS_SVB_FLIP_VERT: SVB_FLIP_STATUS = 2;
+pub const SVB_FLIP_STATUS_SVB_FLIP_BOTH: SVB_FLIP_STATUS = 3;
+pub type SVB_FLIP_STATUS = ::std::os::raw::c_uint;
+pub const SVB_CAMERA_MODE_SVB_MODE_NORMAL: SVB_CAMERA_MODE = 0;
+pub const SVB_CAMERA_MODE_SVB_MODE_TRIG_SOFT: SVB_CAMERA_MODE = 1;
+pub const SVB_CAMERA_MODE_SVB_MODE_TRIG_RISE_EDGE: SVB_CAMERA_MODE = 2;
+pub const SVB_CAMERA_MODE_SVB_MODE_TRIG_FALL_EDGE: SVB_CAMERA_MODE = 3;
+pub const SVB_CAMERA_MODE_SVB_MODE_TRIG_DOUBLE_EDGE: SVB_CAMERA_MODE = 4;
+pub const SVB_CAMERA_MODE_SVB_MODE_TRIG_HIGH_LEVEL: SVB_CAMERA_MODE = 5;
+pub const SVB_CAMERA_MODE_SVB_MODE_TRIG_LOW_LEVEL: SVB_CAMERA_MODE = 6;
+pub const SVB_CAMERA_MODE_SVB_MODE_END: SVB_CAMERA_MODE = -1;
+pub type SVB_CAMERA_MODE = ::std::os::raw::c_int;
+pub const SVB_TRIG_OUTPUT_SVB_TRIG_OUTPUT_PINA: SVB_TRIG_OUTPUT = 0;
+pub const SVB_TRIG_OUTPUT_SVB_TRIG_OUTPUT_PINB: SVB_TRIG_OUTPUT = 1;
+pub const SVB_TRIG_OUTPUT_SVB_TRIG_OUTPUT_NONE: SVB_TRIG_OUTPUT = -1;
+pub type SVB_TRIG_OUTPUT = ::std::os::raw::c_int;
+pub use self::SVB_TRIG_OUTPUT as SVB_TRIG_OUTPUT_PIN;
+pub const SVB_ERROR_CODE_SVB_SUCCESS: SVB_ERROR_CODE = 0;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_INDEX: SVB_ERROR_CODE = 1;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_ID: SVB_ERROR_CODE = 2;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_CONTROL_TYPE: SVB_ERROR_CODE = 3;
+pub const SVB_ERROR_CODE_SVB_ERROR_CAMERA_CLOSED: SVB_ERROR_CODE = 4;
+pub const SVB_ERROR_CODE_SVB_ERROR_CAMERA_REMOVED: SVB_ERROR_CODE = 5;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_PATH: SVB_ERROR_CODE = 6;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_FILEFORMAT: SVB_ERROR_CODE = 7;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_SIZE: SVB_ERROR_CODE = 8;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_IMGTYPE: SVB_ERROR_CODE = 9;
+pub const SVB_ERROR_CODE_SVB_ERROR_OUTOF_BOUNDARY: SVB_ERROR_CODE = 10;
+pub const SVB_ERROR_CODE_SVB_ERROR_TIMEOUT: SVB_ERROR_CODE = 11;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_SEQUENCE: SVB_ERROR_CODE = 12;
+pub const SVB_ERROR_CODE_SVB_ERROR_BUFFER_TOO_SMALL: SVB_ERROR_CODE = 13;
+pub const SVB_ERROR_CODE_SVB_ERROR_VIDEO_MODE_ACTIVE: SVB_ERROR_CODE = 14;
+pub const SVB_ERROR_CODE_SVB_ERROR_EXPOSURE_IN_PROGRESS: SVB_ERROR_CODE = 15;
+pub const SVB_ERROR_CODE_SVB_ERROR_GENERAL_ERROR: SVB_ERROR_CODE = 16;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_MODE: SVB_ERROR_CODE = 17;
+pub const SVB_ERROR_CODE_SVB_ERROR_INVALID_DIRECTION: SVB_ERROR_CODE = 18;
+pub const SVB_ERROR_CODE_SVB_ERROR_UNKNOW_SENSOR_TYPE: SVB_ERROR_CODE = 19;
+pub const SVB_ERROR_CODE_SVB_ERROR_END: SVB_ERROR_CODE = 20;
+pub type SVB_ERROR_CODE = ::std::os::raw::c_uint;
+pub const SVB_BOOL_SVB_FALSE: SVB_BOOL = 0;
+pub const SVB_BOOL_SVB_TRUE: SVB_BOOL = 1;
+pub type SVB_BOOL = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SVB_CAMERA_INFO {
+    pub FriendlyName: [::std::os::raw::c_char; 32usize],
+    pub CameraSN: [::std::os::raw::c_char; 32usize],
+    pub PortType: [::std::os::raw::c_char; 32usize],
+    pub DeviceID: ::std::os::raw::c_uint,
+    pub CameraID: ::std::os::raw::c_int,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SVB_CAMERA_PROPERTY {
+    pub MaxHeight: ::std::os::raw::c_long,
+    pub MaxWidth: ::std::os::raw::c_long,
+    pub IsColorCam: SVB_BOOL,
+    pub BayerPattern: SVB_BAYER_PATTERN,
+    pub SupportedBins: [::std::os::raw::c_int; 16usize],
+    pub SupportedVideoFormat: [SVB_IMG_TYPE; 8usize],
+    pub MaxBitDepth: ::std::os::raw::c_int,
+    pub IsTriggerCam: SVB_BOOL,
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SVB_CAMERA_PROPERTY_EX {
+    pub bSupportPulseGuide: SVB_BOOL,
+    pub bSupportControlTemp: SVB_BOOL,
+    pub Unused: [::std::os::raw::c_int; 64usize],
+}
+pub const SVB_CONTROL_TYPE_SVB_GAIN: SVB_CONTROL_TYPE = 0;
+pub const SVB_CONTROL_TYPE_SVB_EXPOSURE: SVB_CONTROL_TYPE = 1;
+pub const SVB_CONTROL_TYPE_SVB_GAMMA: SVB_CONTROL_TYPE = 2;
+pub const SVB_CONTROL_TYPE_SVB_GAMMA_CONTRAST: SVB_CONTROL_TYPE = 3;
+pub const SVB_CONTROL_TYPE_SVB_WB_R: SVB_CONTROL_TYPE = 4;
+pub const SVB_CONTROL_TYPE_SVB_WB_G: SVB_CONTROL_TYPE = 5;
+pub const SVB_CONTROL_TYPE_SVB_WB_B: SVB_CONTROL_TYPE = 6;
+pub const SVB_CONTROL_TYPE_SVB_FLIP: SVB_CONTROL_TYPE = 7;
+pub const SVB_CONTROL_TYPE_SVB_FRAME_SPEED_MODE: SVB_CONTROL_TYPE = 8;
+pub const SVB_CONTROL_TYPE_SVB_CONTRAST: SVB_CONTROL_TYPE = 9;
+pub const SVB_CONTROL_TYPE_SVB_SHARPNESS: SVB_CONTROL_TYPE = 10;
+pub const SVB_CONTROL_TYPE_SVB_SATURATION: SVB_CONTROL_TYPE = 11;
+pub const SVB_CONTROL_TYPE_SVB_AUTO_TARGET_BRIGHTNESS: SVB_CONTROL_TYPE = 12;
+pub const SVB_CONTROL_TYPE_SVB_BLACK_LEVEL: SVB_CONTROL_TYPE = 13;
+pub const SVB_CONTROL_TYPE_SVB_COOLER_ENABLE: SVB_CONTROL_TYPE = 14;
+pub const SVB_CONTROL_TYPE_SVB_TARGET_TEMPERATURE: SVB_CONTROL_TYPE = 15;
+pub const SVB_CONTROL_TYPE_SVB_CURRENT_TEMPERATURE: SVB_CONTROL_TYPE = 16;
+pub const SVB_CONTROL_TYPE_SVB_COOLER_POWER: SVB_CONTROL_TYPE = 17;
+pub const SVB_CONTROL_TYPE_SVB_BAD_PIXEL_CORRECTION_ENABLE: SVB_CONTROL_TYPE = 18;
+pub type SVB_CONTROL_TYPE = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct _SVB_CONTROL_CAPS {
+    pub Name: [::std::os::raw::c_char; 64usize],
+    pub Description: [::std::os::raw::c_char; 128usize],
+    pub MaxValue: ::std::os::raw::c_long,
+    pub MinValue: ::std::os::raw::c_long,
+    pub DefaultValue: ::std::os::raw::c_long,
+    pub IsAutoSupported: SVB_BOOL,
+    pub IsWritable: SVB_BOOL,
+    pub ControlType: SVB_CONTROL_TYPE,
+    pub Unused: [::std::os::raw::c_char; 32usize],
+}
+pub type SVB_CONTROL_CAPS = _SVB_CONTROL_CAPS;
+pub const SVB_EXPOSURE_STATUS_SVB_EXP_IDLE: SVB_EXPOSURE_STATUS = 0;
+pub const SVB_EXPOSURE_STATUS_SVB_EXP_WORKING: SVB_EXPOSURE_STATUS = 1;
+pub const SVB_EXPOSURE_STATUS_SVB_EXP_SUCCESS: SVB_EXPOSURE_STATUS = 2;
+pub const SVB_EXPOSURE_STATUS_SVB_EXP_FAILED: SVB_EXPOSURE_STATUS = 3;
+pub type SVB_EXPOSURE_STATUS = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct _SVB_ID {
+    pub id: [::std::os::raw::c_uchar; 64usize],
+}
+pub type SVB_ID = _SVB_ID;
+pub type SVB_SN = SVB_ID;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct _SVB_SUPPORTED_MODE {
+    pub SupportedCameraMode: [SVB_CAMERA_MODE; 16usize],
+}
+pub type SVB_SUPPORTED_MODE = _SVB_SUPPORTED_MODE;
+extern "C" {
+    pub fn SVBGetNumOfConnectedCameras() -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetCameraInfo(
+        pSVBCameraInfo: *mut SVB_CAMERA_INFO,
+        iCameraIndex: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetCameraProperty(
+        iCameraID: ::std::os::raw::c_int,
+        pCameraProperty: *mut SVB_CAMERA_PROPERTY,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetCameraPropertyEx(
+        iCameraID: ::std::os::raw::c_int,
+        pCameraPorpertyEx: *mut SVB_CAMERA_PROPERTY_EX,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBOpenCamera(iCameraID: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBCloseCamera(iCameraID: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetNumOfControls(
+        iCameraID: ::std::os::raw::c_int,
+        piNumberOfControls: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetControlCaps(
+        iCameraID: ::std::os::raw::c_int,
+        iControlIndex: ::std::os::raw::c_int,
+        pControlCaps: *mut SVB_CONTROL_CAPS,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetControlValue(
+        iCameraID: ::std::os::raw::c_int,
+        ControlType: ::std::os::raw::c_int,
+        plValue: *mut ::std::os::raw::c_long,
+        pbAuto: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBSetControlValue(
+        iCameraID: ::std::os::raw::c_int,
+        ControlType: ::std::os::raw::c_int,
+        lValue: ::std::os::raw::c_long,
+        bAuto: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetOutputImageType(
+        iCameraID: ::std::os::raw::c_int,
+        pImageType: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBSetOutputImageType(
+        iCameraID: ::std::os::raw::c_int,
+        ImageType: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBSetROIFormat(
+        iCameraID: ::std::os::raw::c_int,
+        iStartX: ::std::os::raw::c_int,
+        iStartY: ::std::os::raw::c_int,
+        iWidth: ::std::os::raw::c_int,
+        iHeight: ::std::os::raw::c_int,
+        iBin: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetROIFormat(
+        iCameraID: ::std::os::raw::c_int,
+        piStartX: *mut ::std::os::raw::c_int,
+        piStartY: *mut ::std::os::raw::c_int,
+        piWidth: *mut ::std::os::raw::c_int,
+        piHeight: *mut ::std::os::raw::c_int,
+        piBin: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetDroppedFrames(
+        iCameraID: ::std::os::raw::c_int,
+        piDropFrames: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBStartVideoCapture(iCameraID: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBStopVideoCapture(iCameraID: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetVideoData(
+        iCameraID: ::std::os::raw::c_int,
+        pBuffer: *mut ::std::os::raw::c_uchar,
+        lBuffSize: ::std::os::raw::c_long,
+        iWaitms: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBWhiteBalanceOnce(iCameraID: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetCameraFirmwareVersion(
+        iCameraID: ::std::os::raw::c_int,
+        pCameraFirmwareVersion: *mut ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetSDKVersion() -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn SVBGetCameraSupportMode(
+        iCameraID: ::std::os::raw::c_int,
+        pSupportedMode: *mut SVB_SUPPORTED_MODE,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetCameraMode(
+        iCameraID: ::std::os::raw::c_int,
+        mode: *mut SVB_CAMERA_MODE,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBSetCameraMode(
+        iCameraID: ::std::os::raw::c_int,
+        mode: SVB_CAMERA_MODE,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBSendSoftTrigger(iCameraID: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetSerialNumber(
+        iCameraID: ::std::os::raw::c_int,
+        pSN: *mut SVB_SN,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBSetTriggerOutputIOConf(
+        iCameraID: ::std::os::raw::c_int,
+        pin: SVB_TRIG_OUTPUT_PIN,
+        bPinHigh: ::std::os::raw::c_int,
+        lDelay: ::std::os::raw::c_long,
+        lDuration: ::std::os::raw::c_long,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetTriggerOutputIOConf(
+        iCameraID: ::std::os::raw::c_int,
+        pin: SVB_TRIG_OUTPUT_PIN,
+        bPinHigh: *mut ::std::os::raw::c_int,
+        lDelay: *mut ::std::os::raw::c_long,
+        lDuration: *mut ::std::os::raw::c_long,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBPulseGuide(
+        iCameraID: ::std::os::raw::c_int,
+        direction: ::std::os::raw::c_int,
+        duration: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBGetSensorPixelSize(
+        iCameraID: ::std::os::raw::c_int,
+        fPixelSize: *mut f32,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBCanPulseGuide(
+        iCameraID: ::std::os::raw::c_int,
+        pCanPulseGuide: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBSetAutoSaveParam(
+        iCameraID: ::std::os::raw::c_int,
+        enable: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBIsCameraNeedToUpgrade(
+        iCameraID: ::std::os::raw::c_int,
+        pIsNeedToUpgrade: *mut ::std::os::raw::c_int,
+        pNeedToUpgradeMinVersion: *mut ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn SVBRestoreDefaultParam(iCameraID: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
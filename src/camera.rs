@@ -1,108 +1,461 @@
+use crate::backend::{Backend, SdkBackend};
+use crate::fits;
+use crate::frame::{self, CaptureStats, Frame, ImageMetadata};
+use crate::guide::{ActivePulses, GuidePulse};
+use crate::optics::Optics;
 use crate::utils;
-use crate::{BufType,BufSize};
+use crate::pool::BufferPool;
+use crate::snap::{CancelToken, SnapOptions, SnapProgress, SNAP_POLL_INTERVAL};
+use crate::stream::{FrameStream, StreamOptions};
+use crate::BufType;
 use crate::{
     debayer, libsvb,
-    libsvb::{convert_err_code, ControlTypeState, ROIFormat, SVBError},
+    libsvb::{
+        BayerPattern, CameraMode, ControlType, ControlTypeState, ExposureStatus, FlipStatus, GuideDirection, ImageType,
+        ROIFormat, SVBError, TriggerOutput, TriggerOutputPin,
+    },
 };
 
+use chrono::Utc;
 use image::{self};
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::Write;
-use std::thread;
-#[derive(Debug, Clone)]
-pub struct Camera {
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// State of a `Camera` which has not been opened yet, or has been closed.
+#[derive(Debug)]
+pub struct Closed;
+/// State of an opened `Camera`; a capturing camera is a `CaptureSession`.
+#[derive(Debug)]
+pub struct Open;
+
+/// Handle of one camera, closed when dropped.
+///
+/// The type parameter tracks the SDK call sequence: a `Camera<Closed>` can
+/// only be initialized, a `Camera<Open>` can be configured and started, and
+/// frames are read from the `CaptureSession` returned by
+/// `Camera::start_video_capture`, during which ROI and image type can't change.
+#[derive(Debug)]
+pub struct Camera<S = Open> {
     pub id: i32,
     pub idx: i32,
     pub info: libsvb::SVB_CAMERA_INFO,
     pub prop: libsvb::SVB_CAMERA_PROPERTY,
-    pub type2caps: HashMap<libsvb::SVB_CONTROL_TYPE, libsvb::SVB_CONTROL_CAPS>,
-    pub roi : ROIFormat
+    description: CameraDescription,
+    pub type2caps: HashMap<ControlType, libsvb::SVB_CONTROL_CAPS>,
+    pub roi : ROIFormat,
+    img_type: ImageType,
+    optics: Option<Optics>,
+    /// Values of manually set controls, dropped whenever the control is set.
+    ctl_values: Arc<Mutex<HashMap<ControlType, libsvb::SVBControlValue>>>,
+    pool: BufferPool,
+    pulses: ActivePulses,
+    backend: Arc<dyn Backend>,
+    is_open: bool,
+    state: PhantomData<S>,
+}
+
+/// Where the ROI and control values of a camera come from when it is opened.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum InitParams {
+    /// Full frame ROI and the default value of every writable control.
+    #[default]
+    Defaults,
+    /// Restore the factory defaults stored in the camera with
+    /// `SVBRestoreDefaultParam`, e.g. to get a unit out of a bad state.
+    FactoryReset,
+    /// Keep the ROI and control values the camera persisted from its last session.
+    KeepPersisted,
+    /// Like `Defaults`, then apply the profile.
+    Profile(CameraProfile),
+}
+
+/// Settings to bring a camera into, see `Camera::profile`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CameraProfile {
+    /// Control values, set in order.
+    pub controls: Vec<(ControlType, ControlTypeState)>,
+    /// ROI to set, `None` keeps the current one.
+    pub roi: Option<ROIFormat>,
+}
+
+/// How `Camera::init_with` sets the camera up after opening it.
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    pub params: InitParams,
+    /// Output image type to start with, `None` keeps the camera's current one.
+    pub img_type: Option<ImageType>,
+    /// Flip to start with, `None` keeps the camera's current one.
+    pub flip: Option<FlipStatus>,
+}
+
+impl Default for InitOptions {
+    /// Full frame ROI, default control values, RAW8 and both axes flipped.
+    fn default() -> Self {
+        Self {
+            params: InitParams::Defaults,
+            img_type: Some(ImageType::Raw8),
+            flip: Some(FlipStatus::Both),
+        }
+    }
+}
+
+impl InitOptions {
+    /// Leave every setting of the camera as it is.
+    pub fn keep_persisted() -> Self {
+        Self {
+            params: InitParams::KeepPersisted,
+            img_type: None,
+            flip: None,
+        }
+    }
+}
+
+/// What a camera is and which firmware it runs, gathered when it is opened.
+#[derive(Debug, Clone, Default)]
+pub struct CameraDescription {
+    pub name: String,
+    pub serial_number: String,
+    pub port_type: String,
+    pub prop: libsvb::SVB_CAMERA_PROPERTY,
+    pub prop_ex: libsvb::SVB_CAMERA_PROPERTY_EX,
+    /// Sensor pixel size in µm, `None` if the SDK doesn't know the sensor.
+    pub pixel_size_um: Option<f32>,
+    /// `None` if the camera didn't tell.
+    pub firmware_version: Option<String>,
+    /// `None` if the SDK didn't tell.
+    pub sdk_version: Option<String>,
+    /// Firmware version the camera needs to be upgraded to, `None` if the
+    /// installed firmware is recent enough for this SDK or the SDK can't tell.
+    pub required_firmware: Option<String>,
+}
+
+impl CameraDescription {
+    pub fn needs_firmware_upgrade(&self) -> bool {
+        self.required_firmware.is_some()
+    }
+}
+
+impl fmt::Display for CameraDescription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (SN {}, {}), firmware {}, SDK {}",
+            self.name,
+            self.serial_number,
+            self.port_type,
+            self.firmware_version.as_deref().unwrap_or("unknown"),
+            self.sdk_version.as_deref().unwrap_or("unknown")
+        )?;
+        if let Some(required) = &self.required_firmware {
+            write!(f, ", needs firmware {}", required)?;
+        }
+        Ok(())
+    }
 }
 
 pub trait ImageProcessor {
     fn save_img(&self, img :  image::RgbImage, extention: &str);
-    fn save_raw(&self, buf: BufType);
-    fn buf_to_img(&self, buffer: BufType, alg: debayer::Demosaic) -> Result<image::RgbImage,String>;
-    fn buf_to_fits(&self, buf: BufType) -> BufType;
+    fn save_raw(&self, frame: &Frame);
+    fn buf_to_img(&self, frame: &Frame, alg: debayer::Demosaic) -> Result<image::RgbImage,String>;
+    fn buf_to_fits(&self, frame: &Frame) -> Result<BufType, SVBError>;
 }
-pub fn get_num_of_camera() -> i32{
-
+pub fn get_num_of_camera() -> Result<i32, SVBError> {
     libsvb::_get_num_of_connected_cameras()
 }
-impl Camera {
-    pub fn new(camera_idx: i32) -> Self {
-        //camera_info.display_info();
 
-        let mut camera = Camera {
+/// A connected camera as reported by `SVBGetCameraInfo`, see `list_cameras`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CameraInfo {
+    /// Index to create the `Camera` with, which changes when cameras are
+    /// plugged in a different order.
+    pub index: i32,
+    pub name: String,
+    pub serial_number: String,
+    pub port_type: String,
+    pub device_id: u32,
+    pub camera_id: i32,
+}
+
+impl CameraInfo {
+    pub fn new(index: i32, info: &libsvb::SVB_CAMERA_INFO) -> Self {
+        Self {
+            index,
+            name: libsvb::c_str_to_string(&info.FriendlyName),
+            serial_number: libsvb::c_str_to_string(&info.CameraSN),
+            port_type: libsvb::c_str_to_string(&info.PortType),
+            device_id: info.DeviceID,
+            camera_id: info.CameraID,
+        }
+    }
+}
+
+/// Every connected camera, without opening any.
+pub fn list_cameras() -> Result<Vec<CameraInfo>, SVBError> {
+    list_cameras_with(&SdkBackend)
+}
+
+/// Like `list_cameras`, for the cameras of `backend`.
+pub fn list_cameras_with(backend: &dyn Backend) -> Result<Vec<CameraInfo>, SVBError> {
+    let num = backend.get_num_of_connected_cameras()?;
+    let cameras: Vec<CameraInfo> = (0..num)
+        .map(|idx| Ok(CameraInfo::new(idx, &backend.get_camera_info(idx)?)))
+        .collect::<Result<_, SVBError>>()?;
+    debug!("Connected cameras {:?}", cameras);
+    Ok(cameras)
+}
+impl Camera<Closed> {
+    pub fn new(camera_idx: i32) -> Self {
+        Self::with_backend(camera_idx, Arc::new(SdkBackend))
+    }
+    /// Create a camera driven by `backend` instead of the SVBCameraSDK,
+    /// e.g. `simulator::SimulatedBackend`.
+    pub fn with_backend(camera_idx: i32, backend: Arc<dyn Backend>) -> Self {
+        Camera {
             id: 0,
             idx: camera_idx,
             info: libsvb::SVB_CAMERA_INFO::new(),
             prop: libsvb::SVB_CAMERA_PROPERTY::new(),
+            description: CameraDescription::default(),
             type2caps: HashMap::new(),
-            roi : ROIFormat::new() 
-        };
-        camera
+            roi : ROIFormat::new(),
+            img_type: ImageType::Raw8,
+            optics: None,
+            ctl_values: Arc::new(Mutex::new(HashMap::new())),
+            pool: BufferPool::default(),
+            pulses: ActivePulses::default(),
+            backend,
+            is_open: false,
+            state: PhantomData,
+        }
     }
-    pub fn init(&mut self) {
+    /// Open the connected camera with serial number `serial` with
+    /// `InitOptions::default()`, wherever it is plugged in.
+    pub fn open_by_serial(serial: &str) -> Result<Camera<Open>, SVBError> {
+        Self::find_with(Arc::new(SdkBackend), |info| info.serial_number == serial)
+            .inspect_err(|e| error!("{} : Failed to find camera with serial number {}", e, serial))?
+            .init()
+    }
+    /// Open the connected camera named `name` with `InitOptions::default()`.
+    /// Fails with `SVBError::AmbiguousCamera` if several cameras have that
+    /// name, which `open_by_serial` tells apart.
+    pub fn open_by_name(name: &str) -> Result<Camera<Open>, SVBError> {
+        Self::find_with(Arc::new(SdkBackend), |info| info.name == name)
+            .inspect_err(|e| error!("{} : Failed to find camera named {}", e, name))?
+            .init()
+    }
+    /// The one camera of `backend` which `matches`, not opened yet.
+    pub fn find_with(
+        backend: Arc<dyn Backend>,
+        matches: impl Fn(&CameraInfo) -> bool,
+    ) -> Result<Self, SVBError> {
+        let found: Vec<CameraInfo> = list_cameras_with(&*backend)?
+            .into_iter()
+            .filter(|info| matches(info))
+            .collect();
+        match found.as_slice() {
+            [info] => {
+                info!("Found camera {} ({}) at index {}", info.name, info.serial_number, info.index);
+                Ok(Self::with_backend(info.index, backend))
+            }
+            [] => Err(SVBError::CameraNotFound),
+            _ => Err(SVBError::AmbiguousCamera(found.len())),
+        }
+    }
+    /// Open the camera with `InitOptions::default()`.
+    pub fn init(self) -> Result<Camera<Open>, SVBError> {
+        self.init_with(InitOptions::default())
+    }
+    /// Open the camera and bring it into the state described by `options`.
+    ///
+    /// If anything fails after the camera has been opened, it is closed again
+    /// before the error is returned.
+    pub fn init_with(mut self, options: InitOptions) -> Result<Camera<Open>, SVBError> {
         // get camera info
-        self.info = libsvb::_get_camera_info(self.idx).unwrap();
+        self.info = self.backend.get_camera_info(self.idx)?;
         debug!("{}", self.info);
         self.id = self.info.CameraID;
 
         // camera open.
-        self.open().unwrap();
+        let mut camera = self.open()?;
+
+        match camera.configure(&options) {
+            Ok(()) => Ok(camera),
+            Err(e) => {
+                // dropping the camera closes it
+                error!("{} : Failed to initialize camera_id {}", e, camera.id);
+                Err(e)
+            }
+        }
+    }
+    fn open(mut self) -> Result<Camera<Open>, SVBError> {
+        match self.backend.open_camera(self.id) {
+            Ok(()) => {
+                self.is_open = true;
+                info!("Opened camera");
+                Ok(self.into_state())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
 
+impl<S> Camera<S> {
+    /// Move the handle into another state, `self` is dropped without closing.
+    fn into_state<T>(mut self) -> Camera<T> {
+        let camera = Camera {
+            id: self.id,
+            idx: self.idx,
+            info: self.info,
+            prop: self.prop,
+            description: std::mem::take(&mut self.description),
+            type2caps: std::mem::take(&mut self.type2caps),
+            roi: self.roi,
+            img_type: self.img_type,
+            optics: self.optics,
+            ctl_values: self.ctl_values.clone(),
+            pool: self.pool.clone(),
+            pulses: self.pulses.clone(),
+            backend: self.backend.clone(),
+            is_open: self.is_open,
+            state: PhantomData,
+        };
+        self.is_open = false;
+        camera
+    }
+}
+
+impl Camera<Open> {
+    fn configure(&mut self, options: &InitOptions) -> Result<(), SVBError> {
         // get camera serial number .
         // Note: If you do not get the serial number first,
         // CameraID will not be recognized and you could not get the property.
-        let sn = self.get_serial_number().unwrap();
+        let serial_number = self.get_serial_number()?;
 
         // get camera property
-        self.prop = match libsvb::_get_camera_prop(self.id) {
-            Ok(prop) => prop,
-            Err(e) => {
-                error!("{} got {}", e, self.id);
-                self.prop
+        self.prop = self.backend.get_camera_prop(self.id)?;
+        debug!("{}", self.prop);
+        self.description = self.describe(&serial_number)?;
+        info!("{}", self.description);
+        if let Some(required) = &self.description.required_firmware {
+            warn!(
+                "Firmware {} of camera_id {} is outdated, upgrade it to {} or newer",
+                self.description.firmware_version.as_deref().unwrap_or("unknown"),
+                self.id,
+                required
+            );
+        }
+        let apply_defaults = match options.params {
+            InitParams::Defaults | InitParams::Profile(_) => true,
+            InitParams::FactoryReset => {
+                self.restore_default_params()?;
+                false
             }
+            InitParams::KeepPersisted => false,
         };
-        debug!("{}", self.prop);
-        self.set_roi_format(0, 0, self.prop.MaxWidth as i32, self.prop.MaxHeight as i32, 1).unwrap();
+        if apply_defaults {
+            self.set_roi_format_snapped(0, 0, self.prop.MaxWidth as i32, self.prop.MaxHeight as i32, 1)?;
+        } else {
+            self.roi = self.get_roi_format()?;
+        }
+
         //get control capability and push to HashMap
-        let num_of_ctls = self.get_num_of_controls().unwrap();
+        self.type2caps.clear();
+        self.ctl_values.lock().unwrap().clear();
+        let num_of_ctls = self.get_num_of_controls()?;
         for ctl_idx in 0..num_of_ctls {
-            let ctl_caps = self.get_ctl_caps_by_idx(ctl_idx).unwrap();
+            let ctl_caps = self.get_ctl_caps_by_idx(ctl_idx)?;
             debug!("{}", ctl_caps);
 
-            if ctl_caps.IsWritable == 0 {
-                continue;
-            }
-            match self.set_ctl_value( ctl_caps.ControlType, ctl_caps.DefaultValue,0){
-                Ok(()) => (),
-                Err(e) => error!("{} : Failed to set default value {} of control type {}", e,ctl_caps.DefaultValue, ctl_caps.ControlType)
+            let ctl_type = match ControlType::try_from(ctl_caps.ControlType) {
+                Ok(ctl_type) => ctl_type,
+                Err(e) => {
+                    warn!("{} : Skipped control {}", e, ctl_idx);
+                    continue;
+                }
+            };
+            // read only controls like the sensor temperature have no default to apply
+            if apply_defaults && ctl_caps.IsWritable != 0 {
+                match self.set_ctl_value(ctl_type, ctl_caps.DefaultValue, 0) {
+                    Ok(()) => (),
+                    Err(e) => error!("{} : Failed to set default value {} of control type {}", e, ctl_caps.DefaultValue, ctl_type)
+                }
             }
-            self.type2caps.insert(ctl_caps.ControlType, ctl_caps);
+            self.type2caps.insert(ctl_type, ctl_caps);
         }
 
-        // set image type to RAW8
-        self.set_img_type(libsvb::SVB_IMG_TYPE_SVB_IMG_RAW8).unwrap();
-
-        // horizontal and vertical flip
-        self.set_ctl_value(libsvb::SVB_CONTROL_TYPE_SVB_FLIP, 3, 0).unwrap();
-
-
-
-    }
-    pub fn open(&self) -> Result<(), SVBError> {
-        match libsvb::_open_camera(self.id) {
-            SVBError::Success => Ok(info!("Opened camera")),
-            e => Err(e),
+        match options.img_type {
+            Some(img_type) => self.set_img_type(img_type)?,
+            None => self.img_type = self.get_img_type()?,
+        }
+        if let Some(flip) = options.flip {
+            self.set_flip(flip)?;
         }
+        if let InitParams::Profile(profile) = &options.params {
+            self.apply_profile(profile)?;
+        }
+        Ok(())
+    }
+    fn describe(&self, serial_number: &libsvb::SVB_SN) -> Result<CameraDescription, SVBError> {
+        let prop_ex = self.backend.get_camera_prop_ex(self.id)?;
+        debug!("{}", prop_ex);
+        let pixel_size_um = match self.backend.get_sensor_pixel_size(self.id) {
+            Ok(pixel_size) => Some(pixel_size),
+            Err(e) => {
+                warn!("{} : Pixel size of camera_id {} is unknown", e, self.id);
+                None
+            }
+        };
+        // only informational, so failing to read them doesn't fail `init`
+        let required_firmware = match self.backend.is_camera_need_to_upgrade(self.id) {
+            Ok((needs_upgrade, required_firmware)) => needs_upgrade.then_some(required_firmware),
+            Err(e) => {
+                warn!("{} : Firmware upgrade status of camera_id {} is unknown", e, self.id);
+                None
+            }
+        };
+        let firmware_version = match self.backend.get_firmware_version(self.id) {
+            Ok(version) => Some(version),
+            Err(e) => {
+                warn!("{} : Firmware version of camera_id {} is unknown", e, self.id);
+                None
+            }
+        };
+        let sdk_version = match self.backend.get_sdk_version() {
+            Ok(version) => Some(version),
+            Err(e) => {
+                warn!("{} : SDK version is unknown", e);
+                None
+            }
+        };
+        Ok(CameraDescription {
+            name: libsvb::c_str_to_string(&self.info.FriendlyName),
+            serial_number: serial_number.to_string(),
+            port_type: libsvb::c_str_to_string(&self.info.PortType),
+            prop: self.prop,
+            prop_ex,
+            pixel_size_um,
+            firmware_version,
+            sdk_version,
+            required_firmware,
+        })
     }
-    pub fn close(&self) -> Result<(), SVBError> {
-        match libsvb::_close_camera(self.id) {
-            SVBError::Success => Ok(info!("Closed camera")),
-            e => Err(e),
+    /// Close the camera, which can then be initialized again.
+    /// Dropping the camera closes it too, but only logs errors.
+    pub fn close(mut self) -> Result<Camera<Closed>, SVBError> {
+        match self.backend.close_camera(self.id) {
+            Ok(()) => {
+                self.is_open = false;
+                info!("Closed camera");
+                Ok(self.into_state())
+            }
+            Err(e) => Err(e),
         }
     }
     pub fn get_info(&self) -> libsvb::SVB_CAMERA_INFO{
@@ -113,129 +466,193 @@ impl Camera {
         self.prop
 
     }
+    /// Properties, versions and pixel size read when the camera was opened.
+    pub fn description(&self) -> &CameraDescription {
+        &self.description
+    }
 
     pub fn get_num_of_controls(&self) -> Result<i32, SVBError> {
-        let mut num_ctls = 0;
-        match libsvb::_get_num_of_controls(self.id, &mut num_ctls) {
-
-            0 => {
+        match self.backend.get_num_of_controls(self.id) {
+            Ok(num_ctls) => {
                 debug!("Num of control types {}", num_ctls);
                 Ok(num_ctls)},
-            code => Err(convert_err_code(code)),
+            Err(e) => Err(e),
         }
     }
 
     pub fn get_ctl_caps_by_idx(&self, ctl_idx: i32) -> Result<libsvb::SVB_CONTROL_CAPS, SVBError> {
-        let mut ctl_caps = libsvb::SVB_CONTROL_CAPS::new();
-        match libsvb::_get_ctl_caps(self.id, ctl_idx, &mut ctl_caps) {
-            0 => Ok(ctl_caps),
-            code => Err(convert_err_code(code)),
-        }
+        self.backend.get_ctl_caps(self.id, ctl_idx)
     }
     pub fn get_ctl_value(
         &self,
-        ctl_type: libsvb::SVB_CONTROL_TYPE,
+        ctl_type: ControlType,
     ) -> Result<ControlTypeState, SVBError> {
-        let mut value: libsvb::SVBControlValue = 0;
-        let mut is_auto = 0;
-        match libsvb::_get_ctl_value(self.id, ctl_type, &mut value, &mut is_auto) {
-            SVBError::Success => {
-                debug!("Get value {} of control type {}", value, ctl_type);
-                Ok(ControlTypeState { value, is_auto })
+        match self.backend.get_ctl_value(self.id, ctl_type.into()) {
+            Ok(state) => {
+                debug!("Get value {} of control type {}", state.value, ctl_type);
+                Ok(state)
             }
-            e => Err(e),
+            Err(e) => Err(e),
         }
     }
     pub fn set_ctl_value(
         &self,
-        ctl_type: libsvb::SVB_CONTROL_TYPE,
+        ctl_type: ControlType,
         value: libsvb::SVBControlValue,
         is_auto: u32,
     ) -> Result<(), SVBError> {
-        match libsvb::_set_ctl_value(self.id, ctl_type, value, is_auto) {
-            SVBError::Success => Ok(debug!("Set value {} of control type {}", value, ctl_type)),
-            e => Err(e),
+        self.ctl_values.lock().unwrap().remove(&ctl_type);
+        match self.backend.set_ctl_value(self.id, ctl_type.into(), value, is_auto) {
+            Ok(()) => {
+                debug!("Set value {} of control type {}", value, ctl_type);
+                Ok(())
+            }
+            Err(e) => Err(e),
         }
     }
-    pub fn start_video_capture(&self) -> Result<(), SVBError> {
-        match libsvb::_start_video_capture(self.id) {
-            SVBError::Success => Ok(info!("Starting video capture on camera_id {}", self.id)),
-            e => Err(e),
+    /// Start video capture. Frames are read through the returned session,
+    /// which stops the capture when it is dropped.
+    pub fn start_video_capture(&mut self) -> Result<CaptureSession<'_>, SVBError> {
+        let reader = self.frame_reader()?;
+        match self.backend.start_video_capture(self.id) {
+            Ok(()) => {
+                info!("Starting video capture on camera_id {}", self.id);
+                Ok(CaptureSession {
+                    camera: self,
+                    is_capturing: true,
+                    sequence: AtomicU64::new(0),
+                    reader,
+                })
+            }
+            Err(e) => Err(e),
         }
     }
-    pub fn stop_video_capture(&self) -> Result<(), SVBError> {
-        match libsvb::_stop_video_capture(self.id) {
-            SVBError::Success => Ok(info!("Stopped video capture on camera_id {}", self.id)),
-            e => Err(e),
+    /// Start video capture with a thread reading the frames into a queue,
+    /// which is drained by iterating over the returned stream.
+    pub fn start_stream(&mut self, options: StreamOptions) -> Result<FrameStream<'_>, SVBError> {
+        let reader = self.frame_reader()?;
+        match self.backend.start_video_capture(self.id) {
+            Ok(()) => {
+                info!("Starting video stream on camera_id {}", self.id);
+                let (backend, id) = (self.backend.clone(), self.id);
+                FrameStream::start(self, reader, options).inspect_err(|_| {
+                    // there is no stream to stop the capture
+                    if let Err(e) = backend.stop_video_capture(id) {
+                        error!("{} : Failed to stop video capture on camera_id {}", e, id);
+                    }
+                })
+            }
+            Err(e) => Err(e),
         }
     }
-    pub fn get_video_data(
-        &self,
-        pbuf: Option<*mut u8>,
-       
-    ) -> Result<Option<BufType>, SVBError> {
-        let buf_size = self.get_buffer_size();
-        let wait_ms = self.get_wait_time();
-        match pbuf {
-            Some(pbuf) => match libsvb::_get_video_data(self.id, pbuf, buf_size, wait_ms) {
-                SVBError::Success => Ok(None),
-                e => Err(e),
-            },
-            None => {
-                let mut buf = self.create_buffer(buf_size);
-                let mut pbuf = buf.as_mut_ptr();
-                match libsvb::_get_video_data(self.id, pbuf, buf_size, wait_ms) {
-                    SVBError::Success => Ok(Some(buf)),
-                    e => Err(e),
-                }
+    /// Take a single frame exposed for `exposure`.
+    ///
+    /// Cameras with `IsTriggerCam` are switched to soft trigger mode for the
+    /// exposure and back afterwards, other cameras take the first frame of a
+    /// video capture. The returned frame carries the settings it was taken with.
+    pub fn snap(&mut self, exposure: Duration, mut options: SnapOptions) -> Result<Frame, SVBError> {
+        let mut report = |status, elapsed| {
+            if let Some(progress) = options.progress.as_mut() {
+                progress(&SnapProgress { status, elapsed, exposure });
+            }
+        };
+        report(ExposureStatus::Idle, Duration::ZERO);
+
+        let result = self.set_ctl_value(ControlType::Exposure, exposure.as_micros() as libsvb::SVBControlValue, 0)
+            .and_then(|()| self.snap_frame(options.cancel.as_ref(), &mut report));
+        match result {
+            Ok(frame) => {
+                report(ExposureStatus::Success, (frame.received_at - frame.requested_at).to_std().unwrap_or_default());
+                info!("Snapped frame of {:?} on camera_id {}", exposure, self.id);
+                Ok(frame)
+            }
+            Err(e) => {
+                report(ExposureStatus::Failed, Duration::ZERO);
+                Err(e)
             }
         }
     }
-    pub fn get_video_frame(
-        &self,
-       
-    ) -> Result<BufType, SVBError> {
-        let buf_size = self.get_buffer_size();
-        let wait_ms = self.get_wait_time();
-        let mut buf = self.create_buffer(buf_size);
-        let mut pbuf = buf.as_mut_ptr();
-        match libsvb::_get_video_data(self.id, pbuf, buf_size, wait_ms) {
-                SVBError::Success => Ok(buf),
-                e => Err(e),
+    fn snap_frame(
+        &mut self,
+        cancel: Option<&CancelToken>,
+        report: &mut dyn FnMut(ExposureStatus, Duration),
+    ) -> Result<Frame, SVBError> {
+        let trigger = self.prop.IsTriggerCam != 0;
+        let previous_mode = match trigger {
+            true => {
+                let mode = self.get_camera_mode()?;
+                self.set_camera_mode(CameraMode::TriggerSoft)?;
+                Some(mode)
+            }
+            false => None,
+        };
+
+        let result = (|| {
+            let session = self.start_video_capture()?;
+            if trigger {
+                session.send_soft_trigger()?;
             }
+            let started_at = Utc::now();
+            let start = Instant::now();
+            let mut poll = || {
+                if cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+                    info!("Snap on camera_id {} cancelled", session.id);
+                    return true;
+                }
+                report(ExposureStatus::Working, start.elapsed());
+                false
+            };
+            let mut frame = session.reader.read_frame_polled(0, &mut poll)?;
+            frame.requested_at = started_at;
+            Ok(frame)
+        })();
 
+        if let Some(mode) = previous_mode {
+            if let Err(e) = self.set_camera_mode(mode) {
+                error!("{} : Failed to restore camera mode {} on camera_id {}", e, mode, self.id);
+            }
+        }
+        result
+    }
+    /// Start video capture without borrowing the camera, for callers such as
+    /// language bindings which start and stop it across calls. Frames are
+    /// counted and pooled as with `start_video_capture`, but nothing keeps the
+    /// ROI or image type from being changed meanwhile: the caller has to refuse
+    /// that until the capture is stopped.
+    pub fn start_detached_capture(&self) -> Result<DetachedCapture, SVBError> {
+        let reader = self.frame_reader()?;
+        match self.backend.start_video_capture(self.id) {
+            Ok(()) => {
+                info!("Starting detached video capture on camera_id {}", self.id);
+                Ok(DetachedCapture {
+                    is_capturing: true,
+                    sequence: AtomicU64::new(0),
+                    reader,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+    pub(crate) fn stop_video_capture(&self) -> Result<(), SVBError> {
+        match self.backend.stop_video_capture(self.id) {
+            Ok(()) => {
+                info!("Stopped video capture on camera_id {}", self.id);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
     }
     pub fn get_roi_format(&self) -> Result<ROIFormat, SVBError> {
-        let camera_id = self.id;
-
-        let mut startx: i32 = 0;
-        let mut starty: i32 = 0;
-        let mut width: i32 = 0;
-        let mut height: i32 = 0;
-        let mut bin: i32 = 1;
-
-        match libsvb::_get_roi_format(
-            camera_id,
-            &mut startx,
-            &mut starty,
-            &mut width,
-            &mut height,
-            &mut bin,
-        ) {
-            SVBError::Success => {
-                let roi = ROIFormat {
-                    startx,
-                    starty,
-                    width,
-                    height,
-                    bin,
-                };
+        match self.backend.get_roi_format(self.id) {
+            Ok(roi) => {
                 debug!("Get ROI Format {}", roi);
                 Ok(roi)
             }
-            e => Err(e),
+            Err(e) => Err(e),
         }
     }
+    /// Sets the ROI, refusing it if `ROIFormat::validate` fails for this sensor.
+    /// The start position and size are in binned pixels.
     pub fn set_roi_format(
         &mut self,
         startx: i32,
@@ -244,93 +661,641 @@ impl Camera {
         height: i32,
         bin: i32,
     ) -> Result<(), SVBError> {
-
-        let max_width = self.prop.MaxWidth as i32;
-        let max_height = self.prop.MaxWidth as i32;
-        if max_height/bin < height || max_width/bin < width {
-            error!("ROI size is over max size. when binning is {}, max height : {}, max width : {}",bin,max_height/bin,max_width/bin);
-            return Err(SVBError::OverFlowResolution);
+        let roi = ROIFormat{ startx, starty, width, height, bin};
+        roi.validate(&self.prop)?;
+        self.apply_roi_format(roi).map(|_| ())
+    }
+    /// Sets the valid ROI nearest to the requested one (see `ROIFormat::snapped`)
+    /// and returns the ROI the camera reports afterwards.
+    pub fn set_roi_format_snapped(
+        &mut self,
+        startx: i32,
+        starty: i32,
+        width: i32,
+        height: i32,
+        bin: i32,
+    ) -> Result<ROIFormat, SVBError> {
+        let requested = ROIFormat{ startx, starty, width, height, bin};
+        let roi = requested.snapped(&self.prop);
+        if roi != requested {
+            info!("ROI snapped from {:?} to {:?}", requested, roi);
         }
-        match libsvb::_set_roi_format(self.id, startx, starty, width, height, bin) {
-            SVBError::Success => {
-                self.roi = ROIFormat{ startx, starty, width, height, bin};
-                Ok(debug!(
-                "set ROI format startx : {}\nstarty:{}\nwidth:{}\nheight:{}\nbin:{}",
-                startx, starty, width, height, bin
-            ))},
+        self.apply_roi_format(roi)
+    }
+    fn apply_roi_format(&mut self, roi: ROIFormat) -> Result<ROIFormat, SVBError> {
+        self.backend.set_roi_format(self.id, roi)?;
+        match self.get_roi_format() {
+            Ok(applied) => {
+                if applied != roi {
+                    warn!("camera applied ROI {:?} instead of {:?}", applied, roi);
+                }
+                self.roi = applied;
+                debug!(
+                    "set ROI format startx : {}\nstarty:{}\nwidth:{}\nheight:{}\nbin:{}",
+                    applied.startx, applied.starty, applied.width, applied.height, applied.bin
+                );
+                Ok(applied)
+            }
 
-            e => Err(e),
+            Err(e) => Err(e),
         }
     }
-    pub fn get_img_type(&self) -> Result<libsvb::SVB_IMG_TYPE, SVBError> {
-        let mut img_type = 0;
-        match libsvb::_get_img_type(self.id, &mut img_type) {
-            SVBError::Success => {
+    pub fn get_img_type(&self) -> Result<ImageType, SVBError> {
+        match self.backend.get_img_type(self.id).and_then(ImageType::try_from) {
+            Ok(img_type) => {
                 debug!("Get image type {}", img_type);
                 Ok(img_type)
             }
-            e => Err(e),
+            Err(e) => Err(e),
         }
     }
-    pub fn set_img_type(&self, img_type: libsvb::SVB_IMG_TYPE) -> Result<(), SVBError> {
-        match libsvb::_set_img_type(self.id, img_type) {
-            SVBError::Success => {
+    pub fn set_img_type(&mut self, img_type: ImageType) -> Result<(), SVBError> {
+        match self.backend.set_img_type(self.id, img_type.into()) {
+            Ok(()) => {
+                self.img_type = img_type;
                 debug!("Set image type {}", img_type);
                 Ok(())
             }
-            e => Err(e),
+            Err(e) => Err(e),
         }
     }
     pub fn get_serial_number(&self) -> Result<libsvb::SVB_SN, SVBError> {
-        let mut sn = libsvb::SVB_SN::new();
-        match libsvb::_get_serial_number(self.id, &mut sn) {
-            SVBError::Success => Ok(sn),
-            e => Err(e),
+        self.backend.get_serial_number(self.id)
+    }
+    /// Size in bytes of a frame with the current ROI and image type.
+    pub fn frame_size(&self) -> usize {
+        self.roi.width as usize * self.roi.height as usize * self.img_type.bytes_per_pixel()
+    }
+    /// Pool the buffers of the frames read from this camera come from.
+    pub fn buffer_pool(&self) -> &BufferPool {
+        &self.pool
+    }
+    /// Sensor pixel size in µm, unbinned.
+    pub fn pixel_size_um(&self) -> Result<f32, SVBError> {
+        match self.backend.get_sensor_pixel_size(self.id) {
+            Ok(pixel_size) => {
+                debug!("Get pixel size {} um", pixel_size);
+                Ok(pixel_size)
+            }
+            Err(e) => Err(e),
         }
     }
-    fn create_buffer(&self, buf_size: BufSize) -> BufType {
-        vec![0; buf_size as usize]
+    /// Optics of this camera's sensor behind a telescope of `focal_length_mm`.
+    pub fn optics(&self, focal_length_mm: f64) -> Result<Optics, SVBError> {
+        Ok(Optics::new(focal_length_mm, self.pixel_size_um()? as f64))
     }
-    fn get_buffer_size(&self) -> BufSize {
-        let roi = self.roi;
-        let img_type = self.get_img_type().unwrap();
-        let mut buf_size: i64 = roi.width as i64 * roi.height as i64;
-
-        // IMG_RAW8 and IMG_Y8 is 1 byte, same size with w*h
-        buf_size = match img_type {
-            libsvb::SVB_IMG_TYPE_SVB_IMG_RAW16 => buf_size * 2,
-            libsvb::SVB_IMG_TYPE_SVB_IMG_RGB24 => buf_size * 3,
-            _ => buf_size,
+    /// Telescope the camera is attached to; frames carry its `ImageGeometry`
+    /// once it is set. Like the ROI, it can't change while capturing.
+    pub fn set_optics(&mut self, optics: Option<Optics>) {
+        if let Some(optics) = &optics {
+            let geometry = optics.geometry(&self.roi);
+            info!(
+                "Set optics {:?}, {:.2}\"/px, FOV {:.1}'x{:.1}', {:?}",
+                optics, geometry.plate_scale, geometry.fov_width_arcmin, geometry.fov_height_arcmin, geometry.sampling
+            );
+        }
+        self.optics = optics;
+    }
+    pub fn get_optics(&self) -> Option<Optics> {
+        self.optics
+    }
+    /// Camera state recorded in FITS and XISF headers besides the frame's own.
+    pub fn image_metadata(&self) -> Result<ImageMetadata, SVBError> {
+        let control = |ctl_type| match self.type2caps.contains_key(&ctl_type) {
+            true => self.cached_ctl_value(ctl_type).map(Some),
+            false => Ok(None),
         };
-        buf_size
+        Ok(ImageMetadata {
+            instrument: self.description.name.clone(),
+            offset: control(ControlType::BlackLevel)?,
+            // reported in 0.1 °C
+            target_temperature: control(ControlType::TargetTemperature)?.map(|t| t as f64 / 10.0),
+            pixel_size_um: self.description.pixel_size_um.map(f64::from),
+        })
     }
-    pub fn get_bayer_pattern(&self) -> u32 {
-        self.prop.BayerPattern
+    pub fn get_bayer_pattern(&self) -> Result<BayerPattern, SVBError> {
+        BayerPattern::try_from(self.prop.BayerPattern)
     }
-    pub fn get_wait_time(&self) -> i32{
-        let wait_ms: i32 = (self
-            .get_ctl_value(libsvb::SVB_CONTROL_TYPE_SVB_EXPOSURE)
-            .unwrap()
-            .value
-            / 1000) as i32
-            * 2
-            + 500;
+    /// Mirror the image horizontally and/or vertically.
+    pub fn set_flip(&self, flip: FlipStatus) -> Result<(), SVBError> {
+        self.set_ctl_value(ControlType::Flip, flip as libsvb::SVBControlValue, 0)
+    }
+    pub fn get_flip(&self) -> Result<FlipStatus, SVBError> {
+        let state = self.get_ctl_value(ControlType::Flip)?;
+        FlipStatus::try_from(state.value as u32)
+    }
+    /// Frames the SDK dropped since the capture was started, 0 when not capturing.
+    pub fn dropped_frames(&self) -> Result<i32, SVBError> {
+        match self.backend.get_dropped_frames(self.id) {
+            Ok(dropped) => {
+                debug!("Dropped frames {}", dropped);
+                Ok(dropped)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    pub fn get_wait_time(&self) -> Result<i32, SVBError> {
+        let wait_ms = wait_time(self.cached_ctl_value(ControlType::Exposure)?);
         debug!("To get frame interval time is {}",wait_ms);
-        wait_ms
+        Ok(wait_ms)
+    }
+    /// Value of `ctl_type`, queried from the camera only if it is set to auto,
+    /// read only, or has been set since it was last queried.
+    fn cached_ctl_value(&self, ctl_type: ControlType) -> Result<libsvb::SVBControlValue, SVBError> {
+        cached_ctl_value(&*self.backend, self.id, &self.type2caps, &self.ctl_values, ctl_type)
+    }
+    /// Snapshot of what reading a frame with the current settings needs.
+    pub(crate) fn frame_reader(&self) -> Result<FrameReader, SVBError> {
+        let sensor_bayer_pattern = match self.prop.IsColorCam != 0 {
+            true => Some(self.get_bayer_pattern()?),
+            false => None,
+        };
+        Ok(FrameReader {
+            id: self.id,
+            backend: self.backend.clone(),
+            roi: self.roi,
+            img_type: self.img_type,
+            optics: self.optics,
+            sensor_bayer_pattern,
+            type2caps: self.type2caps.clone(),
+            ctl_values: self.ctl_values.clone(),
+            pool: self.pool.clone(),
+            stats: Arc::new(Mutex::new(CaptureStats::default())),
+        })
+    }
+    /// Modes the camera supports; only `Normal` unless it has `IsTriggerCam`.
+    pub fn get_supported_modes(&self) -> Result<Vec<CameraMode>, SVBError> {
+        match self.backend.get_camera_support_mode(self.id) {
+            Ok(supported) => {
+                let modes: Vec<CameraMode> = supported
+                    .modes()
+                    .into_iter()
+                    .filter_map(|mode| match CameraMode::try_from(mode) {
+                        Ok(mode) => Some(mode),
+                        Err(e) => {
+                            warn!("{} : Skipped camera mode {}", e, mode);
+                            None
+                        }
+                    })
+                    .collect();
+                debug!("Supported camera modes {:?}", modes);
+                Ok(modes)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    pub fn get_camera_mode(&self) -> Result<CameraMode, SVBError> {
+        match self.backend.get_camera_mode(self.id) {
+            Ok(mode) => {
+                let mode = CameraMode::try_from(mode)?;
+                debug!("Get camera mode {}", mode);
+                Ok(mode)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// Switch between video mode and the trigger modes. Like the ROI, the mode
+    /// can't be changed while capturing.
+    pub fn set_camera_mode(&mut self, mode: CameraMode) -> Result<(), SVBError> {
+        if !self.get_supported_modes()?.contains(&mode) {
+            error!("camera mode {} is not supported by camera_id {}", mode, self.id);
+            return Err(SVBError::InvalidMode);
+        }
+        match self.backend.set_camera_mode(self.id, mode.into()) {
+            Ok(()) => {
+                info!("Set camera mode {}", mode);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// Configure `pin` of the trigger port to become active `delay` after the
+    /// camera receives a trigger and stay active for `duration`, e.g. to fire
+    /// a strobe. Both can be at most `TriggerOutput::MAX_TIME`; a zero
+    /// `duration` disables the pin.
+    pub fn set_trigger_output(
+        &self,
+        pin: TriggerOutputPin,
+        active_high: bool,
+        delay: Duration,
+        duration: Duration,
+    ) -> Result<(), SVBError> {
+        let output = TriggerOutput {
+            active_high,
+            delay,
+            duration,
+        };
+        output.validate()?;
+        match self.backend.set_trigger_output_io_conf(self.id, pin.into(), output) {
+            Ok(()) => {
+                info!("Set trigger output pin {} to {:?}", pin, output);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+    pub fn get_trigger_output(&self, pin: TriggerOutputPin) -> Result<TriggerOutput, SVBError> {
+        match self.backend.get_trigger_output_io_conf(self.id, pin.into()) {
+            Ok(output) => {
+                debug!("Get trigger output pin {} {:?}", pin, output);
+                Ok(output)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// Stop driving `pin`, keeping its active level.
+    pub fn disable_trigger_output(&self, pin: TriggerOutputPin) -> Result<(), SVBError> {
+        let output = self.get_trigger_output(pin)?;
+        self.set_trigger_output(pin, output.active_high, Duration::ZERO, Duration::ZERO)
+    }
+    /// Whether the mount can be guided through the ST-4 port of the camera.
+    pub fn can_pulse_guide(&self) -> Result<bool, SVBError> {
+        match self.backend.can_pulse_guide(self.id) {
+            Ok(can_pulse_guide) => {
+                debug!("Can pulse guide {}", can_pulse_guide);
+                Ok(can_pulse_guide)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// Move the mount in `direction` for `duration`, in whole ms, and return
+    /// once the pulse is over. Fails with `SVBError::PulseGuideInProgress`
+    /// while a pulse on the same axis is running.
+    pub fn pulse_guide(&self, direction: GuideDirection, duration: Duration) -> Result<(), SVBError> {
+        self.pulses.run(&*self.backend, self.id, direction, duration)
+    }
+    /// Like `pulse_guide`, but returns right away, e.g. to guide both axes at
+    /// once or while reading frames.
+    pub fn start_pulse_guide(&self, direction: GuideDirection, duration: Duration) -> Result<GuidePulse, SVBError> {
+        match self.pulses.start(self.backend.clone(), self.id, direction, duration) {
+            Ok(pulse) => {
+                debug!("Started {} pulse of {:?}, done at {:?}", direction, duration, pulse.finishes_at());
+                Ok(pulse)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// Whether the camera saves its parameters, so that they are still set
+    /// the next time it is opened with `InitParams::KeepPersisted`.
+    pub fn set_auto_save_params(&self, enable: bool) -> Result<(), SVBError> {
+        match self.backend.set_auto_save_param(self.id, enable) {
+            Ok(()) => {
+                info!("Set auto save params {} on camera_id {}", enable, self.id);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// Reset ROI, image type and controls to the factory defaults stored in the camera.
+    pub fn restore_default_params(&mut self) -> Result<(), SVBError> {
+        match self.backend.restore_default_param(self.id) {
+            Ok(()) => {
+                info!("Restored default params on camera_id {}", self.id);
+                self.ctl_values.lock().unwrap().clear();
+                self.roi = self.get_roi_format()?;
+                self.img_type = self.get_img_type()?;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// Current ROI and values of the writable controls, to be applied again
+    /// with `apply_profile` or `InitParams::Profile`.
+    pub fn profile(&self) -> Result<CameraProfile, SVBError> {
+        let mut ctl_types: Vec<ControlType> = self
+            .type2caps
+            .iter()
+            .filter(|(_, caps)| caps.IsWritable != 0)
+            .map(|(&ctl_type, _)| ctl_type)
+            .collect();
+        ctl_types.sort_by_key(|&ctl_type| u32::from(ctl_type));
+        let controls = ctl_types
+            .into_iter()
+            .map(|ctl_type| Ok((ctl_type, self.get_ctl_value(ctl_type)?)))
+            .collect::<Result<_, SVBError>>()?;
+        Ok(CameraProfile {
+            controls,
+            roi: Some(self.roi),
+        })
+    }
+    pub fn apply_profile(&mut self, profile: &CameraProfile) -> Result<(), SVBError> {
+        for (ctl_type, state) in &profile.controls {
+            self.set_ctl_value(*ctl_type, state.value, state.is_auto as u32)?;
+        }
+        if let Some(roi) = profile.roi {
+            self.set_roi_format(roi.startx, roi.starty, roi.width, roi.height, roi.bin)?;
+        }
+        info!("Applied profile to camera_id {}", self.id);
+        Ok(())
     }
     pub fn adjust_white_blance(&self,) -> Result<(), SVBError> {
-        match libsvb::_adjust_white_balance(self.id){
-            SVBError::Success => {
+        match self.backend.adjust_white_balance(self.id){
+            Ok(()) => {
                 debug!("Adjusted white balance");
                 Ok(())
             }
-            e => Err(e),
+            Err(e) => Err(e),
         }
     }
 
     
 }
 
+fn cached_ctl_value(
+    backend: &dyn Backend,
+    camera_id: i32,
+    type2caps: &HashMap<ControlType, libsvb::SVB_CONTROL_CAPS>,
+    ctl_values: &Mutex<HashMap<ControlType, libsvb::SVBControlValue>>,
+    ctl_type: ControlType,
+) -> Result<libsvb::SVBControlValue, SVBError> {
+    if let Some(&value) = ctl_values.lock().unwrap().get(&ctl_type) {
+        return Ok(value);
+    }
+    let state = backend.get_ctl_value(camera_id, ctl_type.into())?;
+    let writable = type2caps.get(&ctl_type).is_some_and(|caps| caps.IsWritable != 0);
+    if writable && state.is_auto == 0 {
+        ctl_values.lock().unwrap().insert(ctl_type, state.value);
+    }
+    Ok(state.value)
+}
+
+/// Time to wait for a frame exposed for `exposure` µs.
+fn wait_time(exposure: libsvb::SVBControlValue) -> i32 {
+    (exposure / 1000) as i32 * 2 + 500
+}
+
+impl<S> Drop for Camera<S> {
+    fn drop(&mut self) {
+        if !self.is_open {
+            return;
+        }
+        match self.backend.close_camera(self.id) {
+            Ok(()) => info!("Closed camera"),
+            Err(e) => error!("{} : Failed to close camera_id {}", e, self.id),
+        }
+    }
+}
+
+/// Video capture running on a camera, see `Camera::start_video_capture`.
+///
+/// Frames can only be read while the session exists. It dereferences to the
+/// camera for reading and changing controls; ROI and image type can't be
+/// changed until the capture is stopped. Dropping the session stops the capture.
+///
+/// ```compile_fail
+/// # use svbony_camera_rs::camera::Camera;
+/// let mut camera = Camera::new(0).init().unwrap();
+/// let session = camera.start_video_capture().unwrap();
+/// // the ROI can't be changed while capturing
+/// camera.set_roi_format(0, 0, 640, 480, 1).unwrap();
+/// session.get_video_frame().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct CaptureSession<'a> {
+    camera: &'a mut Camera,
+    is_capturing: bool,
+    sequence: AtomicU64,
+    reader: FrameReader,
+}
+
+impl<'a> CaptureSession<'a> {
+    /// Read the next frame into the start of `buf`, which must hold at least
+    /// `Camera::frame_size` bytes.
+    pub fn get_video_data(&self, buf: &mut [u8]) -> Result<(), SVBError> {
+        self.reader.read_into(buf)
+    }
+    /// Read the next frame together with the settings it was captured with.
+    pub fn get_video_frame(&self) -> Result<Frame, SVBError> {
+        self.reader.read_frame(self.sequence.fetch_add(1, Ordering::Relaxed))
+    }
+    /// Frames received, reads timed out and frames dropped by the SDK since
+    /// the capture was started.
+    pub fn stats(&self) -> Result<CaptureStats, SVBError> {
+        let mut stats = self.reader.stats();
+        stats.dropped = self.dropped_frames()?.max(0) as u64;
+        Ok(stats)
+    }
+    /// Start an exposure in `CameraMode::TriggerSoft`.
+    pub fn send_soft_trigger(&self) -> Result<(), SVBError> {
+        match self.backend.send_soft_trigger(self.id) {
+            Ok(()) => {
+                debug!("Sent soft trigger to camera_id {}", self.id);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+    /// Send a soft trigger and wait for the frame it exposes.
+    pub fn trigger_frame(&self) -> Result<Frame, SVBError> {
+        self.send_soft_trigger()?;
+        self.get_video_frame()
+    }
+    /// Stop the capture, reporting the error dropping the session would only log.
+    pub fn stop(mut self) -> Result<(), SVBError> {
+        self.is_capturing = false;
+        self.camera.stop_video_capture()
+    }
+}
+
+impl<'a> Deref for CaptureSession<'a> {
+    type Target = Camera;
+    fn deref(&self) -> &Camera {
+        self.camera
+    }
+}
+
+impl<'a> Drop for CaptureSession<'a> {
+    fn drop(&mut self) {
+        if !self.is_capturing {
+            return;
+        }
+        if let Err(e) = self.camera.stop_video_capture() {
+            error!("{} : Failed to stop video capture on camera_id {}", e, self.camera.id);
+        }
+    }
+}
+
+/// Video capture which doesn't borrow its camera, see
+/// `Camera::start_detached_capture`. Dropping it stops the capture.
+#[derive(Debug)]
+pub struct DetachedCapture {
+    is_capturing: bool,
+    sequence: AtomicU64,
+    reader: FrameReader,
+}
+
+impl DetachedCapture {
+    /// Read the next frame together with the settings it was captured with.
+    pub fn get_video_frame(&self) -> Result<Frame, SVBError> {
+        self.reader.read_frame(self.sequence.fetch_add(1, Ordering::Relaxed))
+    }
+    /// Frames received and reads timed out since the capture was started, and
+    /// the frames dropped by the SDK as of the last frame.
+    pub fn stats(&self) -> CaptureStats {
+        self.reader.stats()
+    }
+    /// Stop the capture, reporting the error dropping it would only log.
+    pub fn stop(mut self) -> Result<(), SVBError> {
+        self.is_capturing = false;
+        self.stop_video_capture()
+    }
+    fn stop_video_capture(&self) -> Result<(), SVBError> {
+        match self.reader.backend.stop_video_capture(self.reader.id) {
+            Ok(()) => {
+                info!("Stopped video capture on camera_id {}", self.reader.id);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for DetachedCapture {
+    fn drop(&mut self) {
+        if !self.is_capturing {
+            return;
+        }
+        if let Err(e) = self.stop_video_capture() {
+            error!("{} : Failed to stop video capture on camera_id {}", e, self.reader.id);
+        }
+    }
+}
+
+/// What reading a frame needs from a camera, so frames can be read without
+/// borrowing it. ROI and image type are copied, so a reader is only valid for
+/// the capture it was made for.
+#[derive(Debug, Clone)]
+pub(crate) struct FrameReader {
+    id: i32,
+    backend: Arc<dyn Backend>,
+    roi: ROIFormat,
+    img_type: ImageType,
+    optics: Option<Optics>,
+    sensor_bayer_pattern: Option<BayerPattern>,
+    type2caps: HashMap<ControlType, libsvb::SVB_CONTROL_CAPS>,
+    ctl_values: Arc<Mutex<HashMap<ControlType, libsvb::SVBControlValue>>>,
+    pool: BufferPool,
+    stats: Arc<Mutex<CaptureStats>>,
+}
+
+impl FrameReader {
+    fn cached_ctl_value(&self, ctl_type: ControlType) -> Result<libsvb::SVBControlValue, SVBError> {
+        cached_ctl_value(&*self.backend, self.id, &self.type2caps, &self.ctl_values, ctl_type)
+    }
+    fn frame_size(&self) -> usize {
+        self.roi.width as usize * self.roi.height as usize * self.img_type.bytes_per_pixel()
+    }
+    pub(crate) fn read_into(&self, buf: &mut [u8]) -> Result<(), SVBError> {
+        let frame_size = self.frame_size();
+        if buf.len() < frame_size {
+            error!("buffer of {} bytes is smaller than the frame size {}", buf.len(), frame_size);
+            return Err(SVBError::BufferTooSmall);
+        }
+        let wait_ms = wait_time(self.cached_ctl_value(ControlType::Exposure)?);
+        let result = self.backend.get_video_data(self.id, &mut buf[..frame_size], wait_ms);
+        self.count(&result);
+        result
+    }
+    /// `get_video_data` in steps of `SNAP_POLL_INTERVAL`, calling `poll` before
+    /// each step so a long exposure can report progress or be given up on.
+    /// Only running out of `wait_ms` is a timeout.
+    fn poll_video_data(&self, buf: &mut [u8], wait_ms: i32, poll: &mut dyn FnMut() -> bool) -> Result<(), SVBError> {
+        let deadline = Instant::now() + Duration::from_millis(wait_ms.max(0) as u64);
+        loop {
+            if poll() {
+                return Err(SVBError::Cancelled);
+            }
+            let wait = deadline.saturating_duration_since(Instant::now()).min(SNAP_POLL_INTERVAL);
+            match self.backend.get_video_data(self.id, buf, wait.as_millis().max(1) as i32) {
+                Err(e) if matches!(e.root(), SVBError::Timeout) && Instant::now() < deadline => continue,
+                result => return result,
+            }
+        }
+    }
+    /// Count a frame or a timeout.
+    fn count(&self, result: &Result<(), SVBError>) -> CaptureStats {
+        let mut stats = self.stats.lock().unwrap();
+        match result {
+            Ok(()) => stats.received += 1,
+            Err(e) if matches!(e.root(), SVBError::Timeout) => stats.timeouts += 1,
+            Err(_) => (),
+        }
+        *stats
+    }
+    pub(crate) fn stats(&self) -> CaptureStats {
+        *self.stats.lock().unwrap()
+    }
+    pub(crate) fn read_frame(&self, sequence: u64) -> Result<Frame, SVBError> {
+        let exposure = self.cached_ctl_value(ControlType::Exposure)?;
+        self.read_frame_with(sequence, wait_time(exposure), None)
+    }
+    /// Like `read_frame`, but calls `poll` every `SNAP_POLL_INTERVAL` while
+    /// waiting and fails with `SVBError::Cancelled` as soon as it returns true.
+    /// Only the frame or the final timeout is counted.
+    pub(crate) fn read_frame_polled(&self, sequence: u64, poll: &mut dyn FnMut() -> bool) -> Result<Frame, SVBError> {
+        let exposure = self.cached_ctl_value(ControlType::Exposure)?;
+        self.read_frame_with(sequence, wait_time(exposure), Some(poll))
+    }
+    fn read_frame_with(
+        &self,
+        sequence: u64,
+        wait_ms: i32,
+        poll: Option<&mut dyn FnMut() -> bool>,
+    ) -> Result<Frame, SVBError> {
+        let exposure = self.cached_ctl_value(ControlType::Exposure)?;
+        let mut data = self.pool.get(self.frame_size());
+
+        let requested_at = Utc::now();
+        let result = match poll {
+            Some(poll) => self.poll_video_data(&mut data, wait_ms, poll),
+            None => self.backend.get_video_data(self.id, &mut data, wait_ms),
+        };
+        let received_at = Utc::now();
+        let mut stats = self.count(&result);
+        result?;
+
+        let gain = self.cached_ctl_value(ControlType::Gain)?;
+        let temperature = match self.type2caps.contains_key(&ControlType::CurrentTemperature) {
+            // reported in 0.1 °C
+            true => Some(self.cached_ctl_value(ControlType::CurrentTemperature)? as f64 / 10.0),
+            false => None,
+        };
+        let bayer_pattern = match self.sensor_bayer_pattern {
+            Some(sensor) if self.img_type.is_raw() => {
+                let flip = match self.type2caps.contains_key(&ControlType::Flip) {
+                    true => FlipStatus::try_from(self.cached_ctl_value(ControlType::Flip)? as u32)?,
+                    false => FlipStatus::None,
+                };
+                frame::frame_bayer_pattern(sensor, &self.roi, flip)
+            }
+            _ => None,
+        };
+        match self.backend.get_dropped_frames(self.id) {
+            Ok(dropped) => {
+                stats.dropped = dropped.max(0) as u64;
+                self.stats.lock().unwrap().dropped = stats.dropped;
+            }
+            // the frame is good, it keeps the last known count
+            Err(e) => warn!("{} : Failed to read the dropped frames of camera_id {}", e, self.id),
+        }
+
+        Ok(Frame {
+            data,
+            roi: self.roi,
+            img_type: self.img_type,
+            bayer_pattern,
+            exposure,
+            gain,
+            temperature,
+            sequence,
+            requested_at,
+            received_at,
+            stats,
+            geometry: self.optics.map(|optics| optics.geometry(&self.roi)),
+        })
+    }
+}
+
 impl ImageProcessor for Camera {
     fn save_img(&self, img: image::RgbImage, extention: &str) {
         let ext = match extention {
@@ -345,7 +1310,7 @@ impl ImageProcessor for Camera {
             Err(e) => panic!("Failed to save image : {}", e),
         }
     }
-    fn save_raw(&self, buf: BufType) {
+    fn save_raw(&self, frame: &Frame) {
         let output_path = utils::generate_filename("raw");
         let mut file = match File::create(&output_path) {
             Ok(file) => file,
@@ -356,27 +1321,25 @@ impl ImageProcessor for Camera {
         };
 
         // バッファの内容をファイルに書き込む
-        match file.write_all(&buf) {
+        match file.write_all(&frame.data) {
             Ok(_) => debug!("Buffer saved to  {}", output_path),
             Err(e) => eprintln!("Failed to save buffer {:?}", e),
         }
     }
-    fn buf_to_img(&self, buffer: BufType, alg: debayer::Demosaic) -> Result<image::RgbImage, String> {
-        let roi = self.roi;
-        let width = roi.width as u32;
-        let height = roi.height as u32;
-        let img_type = self.get_img_type().unwrap();
-        let bayer_idx = self.get_bayer_pattern();
-        let bayer_pattern = debayer::cfa_from_u32(bayer_idx);
-        let runtime = debayer::Debayer::new(width, height, bayer_pattern);
+    fn buf_to_img(&self, frame: &Frame, alg: debayer::Demosaic) -> Result<image::RgbImage, String> {
+        let bayer_pattern = match frame.bayer_pattern {
+            Some(bayer_pattern) => bayer_pattern,
+            None => return Err(format!("{} frame has no bayer pattern", frame.img_type)),
+        };
+        let runtime = debayer::Debayer::new(frame.width(), frame.height(), bayer_pattern);
         // convert to image by image type (RAW8,RAW16,RGB24,Y8)
-        let debayer_buf = match img_type {
-            libsvb::SVB_IMG_TYPE_SVB_IMG_RAW8 => {
-                runtime.run_from_buf(buffer, debayer::Depth::Depth8, alg)
+        let debayer_buf = match frame.img_type {
+            ImageType::Raw8 => {
+                runtime.run_from_buf(&frame.data, debayer::Depth::Depth8, alg)
             }
 
-            libsvb::SVB_IMG_TYPE_SVB_IMG_RAW16 => {
-                runtime.run_from_buf(buffer, debayer::Depth::Depth16LE, alg)
+            ImageType::Raw16 => {
+                runtime.run_from_buf(&frame.data, debayer::Depth::Depth16LE, alg)
             }
 
             _ => {error!("Not supoorted image type"); Err(bayer::BayerError::WrongDepth)}
@@ -385,96 +1348,394 @@ impl ImageProcessor for Camera {
         Ok(runtime.buffer_to_rgb_image(&debayer_buf.unwrap()).unwrap())
     }
 
-    /// buffer convert to fits format
-    fn buf_to_fits(&self, buf: BufType) -> BufType {
-        // amount of padding
-        fn padding(n: usize) -> usize {
-            match n % 2880 {
-                0 => 0,
-                a => 2880 - a,
-            }
-        }
-
-        let mut fits = Vec::new();
-        let roi = self.roi;
-        let img_t = self.get_img_type().unwrap();
-
-        let (w, h) = (
-            if roi.width < 1000 {
-                format!(" {}", roi.width)
-            } else {
-                format!("{}", roi.width)
-            },
-            if roi.height < 1000 {
-                format!(" {}", roi.height)
-            } else {
-                format!("{}", roi.height)
-            },
-        );
-
-        let bit = match img_t {
-            libsvb::SVB_IMG_TYPE_SVB_IMG_RAW16 => "16",
-            libsvb::SVB_IMG_TYPE_SVB_IMG_RAW8 => " 8",
-            _ => panic!("Fits format is not supported RGB format"),
-        };
-
-        // section number of row is 80
-        let header = [
-            "SIMPLE  =                    T / FITS standard                                  ",
-            &("BITPIX  =                   ".to_owned()
-                + bit
-                + " / bits per pixel                                 "),
-            "NAXIS   =                    2 / number of axis                                 ",
-            &("NAXIS1   =                ".to_owned()
-                + w.as_str()
-                + " / length of data axis 1                          "),
-            &("NAXIS2   =                ".to_owned()
-                + h.as_str()
-                + " / length of data axis 2                          "),
-            "END                                                                             ",
-        ];
-
-        // header section
-        for (i, h) in header.into_iter().enumerate() {
-            if h.len() != 80 {
-                error!("length of header {} is {}, its must be 80.", i, h.len());
-            }
-            for b in h.as_bytes() {
-                fits.push(*b);
-            }
-        }
-
-        // length of header section and data section is 2880
-        // padding using empty(32) to 2880
-        for _ in 0..padding(fits.len()) {
-            fits.push(32);
-        }
-
-        // data section
-        for data in &buf {
-            fits.push(*data)
-        }
-
-        fits
+    /// Frame as a FITS file, with the observation keywords of `fits::Header::for_frame`.
+    fn buf_to_fits(&self, frame: &Frame) -> Result<BufType, SVBError> {
+        let header = fits::Header::for_frame(frame, &self.image_metadata()?);
+        Ok(fits::to_bytes(&header, &fits::Image::from_frame(frame)))
     }
 }
 
+#[cfg(test)]
 mod test {
 
-    use crate::libsvb;
+    use crate::backend::Backend;
+    use crate::libsvb::{
+        self, BayerPattern, CameraMode, ControlType, FlipStatus, ImageType, ROIFormat, SVBError, TriggerOutputPin,
+    };
+    use crate::simulator::{SimulatedBackend, SimulatedCameraConfig};
 
-    use super::Camera;
+    use super::{Camera, InitOptions, InitParams};
+    use crate::libsvb::ExposureStatus;
+    use crate::snap::{CancelToken, SnapOptions, SnapProgress, SNAP_POLL_INTERVAL};
     use env_logger;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    fn simulated_backend() -> Arc<SimulatedBackend> {
+        Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            num_stars: 20,
+            ..Default::default()
+        }]))
+    }
 
     #[test]
     fn test_new() {
         env_logger::init();
-        let num = libsvb::_get_num_of_connected_cameras();
+        let num = libsvb::_get_num_of_connected_cameras().unwrap_or(0);
         if num == 0 {
             error!("not connected camera")
         } else {
-            let camera = Camera::new(0);
-            camera.close();
+            let camera = Camera::new(0).init().unwrap();
+            camera.close().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_description() {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            upgrade_to_firmware: Some("1.2.0".to_string()),
+            ..Default::default()
+        }]));
+        let camera = Camera::with_backend(0, backend).init().unwrap();
+        let description = camera.description();
+        assert_eq!(description.name, "SVBONY SV405CC (simulated)");
+        assert_eq!(description.serial_number, "SIM0000000000001");
+        assert_eq!(description.prop.MaxWidth, 320);
+        assert_eq!(description.prop_ex.bSupportControlTemp, 1);
+        assert_eq!(description.pixel_size_um, Some(4.63));
+        assert_eq!(description.firmware_version.as_deref(), Some("1.0.0"));
+        assert!(description.needs_firmware_upgrade());
+        assert!(description.to_string().ends_with("needs firmware 1.2.0"));
+
+        let camera = Camera::with_backend(0, simulated_backend()).init().unwrap();
+        assert_eq!(camera.description().required_firmware, None);
+    }
+
+    #[test]
+    fn test_init_rollback() {
+        let backend = simulated_backend();
+        let camera = Camera::with_backend(0, backend.clone());
+        // RAW12 is not supported by the simulated camera
+        let options = InitOptions {
+            img_type: Some(ImageType::Raw12),
+            ..Default::default()
+        };
+        assert!(matches!(camera.init_with(options), Err(e) if matches!(e.root(), SVBError::InvalidImgType)));
+        assert!(matches!(backend.get_roi_format(0), Err(e) if matches!(e.root(), SVBError::CameraClosed)));
+
+        let camera = Camera::with_backend(1, backend);
+        assert!(matches!(camera.init(), Err(e) if matches!(e.root(), SVBError::InvalidIndex)));
+    }
+
+    #[test]
+    fn test_init_keep_persisted() {
+        let backend = simulated_backend();
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        assert_eq!(camera.get_img_type().unwrap(), ImageType::Raw8);
+        assert_eq!(camera.get_flip().unwrap(), FlipStatus::Both);
+        camera.set_roi_format(16, 8, 160, 120, 1).unwrap();
+        camera.set_img_type(ImageType::Raw16).unwrap();
+        camera.set_ctl_value(ControlType::Gain, 120, 0).unwrap();
+        let camera = camera.close().unwrap();
+
+        let camera = camera.init_with(InitOptions::keep_persisted()).unwrap();
+        let roi = camera.get_roi_format().unwrap();
+        assert_eq!((roi.startx, roi.starty, roi.width, roi.height), (16, 8, 160, 120));
+        assert_eq!(camera.roi.width, 160);
+        assert_eq!(camera.get_img_type().unwrap(), ImageType::Raw16);
+        assert_eq!(camera.get_ctl_value(ControlType::Gain).unwrap().value, 120);
+        assert!(camera.type2caps.contains_key(&ControlType::Exposure));
+    }
+
+    #[test]
+    fn test_init_params() {
+        let backend = simulated_backend();
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        let default_gain = camera.get_ctl_value(ControlType::Gain).unwrap().value;
+        camera.set_roi_format(16, 8, 160, 120, 1).unwrap();
+        camera.set_ctl_value(ControlType::Gain, 120, 0).unwrap();
+        let profile = camera.profile().unwrap();
+
+        // changes made while auto save is off are lost on close
+        camera.set_auto_save_params(false).unwrap();
+        camera.set_ctl_value(ControlType::Gain, 200, 0).unwrap();
+        let camera = camera.close().unwrap();
+        let camera = camera.init_with(InitOptions::keep_persisted()).unwrap();
+        assert_eq!(camera.get_ctl_value(ControlType::Gain).unwrap().value, 120);
+        camera.set_auto_save_params(true).unwrap();
+
+        let options = InitOptions {
+            params: InitParams::FactoryReset,
+            ..Default::default()
+        };
+        let camera = camera.close().unwrap().init_with(options).unwrap();
+        assert_eq!(camera.get_ctl_value(ControlType::Gain).unwrap().value, default_gain);
+        assert_eq!((camera.roi.width, camera.roi.height), (320, 240));
+
+        let options = InitOptions {
+            params: InitParams::Profile(profile),
+            ..Default::default()
+        };
+        let camera = camera.close().unwrap().init_with(options).unwrap();
+        assert_eq!(camera.get_ctl_value(ControlType::Gain).unwrap().value, 120);
+        assert_eq!((camera.roi.startx, camera.roi.width), (16, 160));
+    }
+
+    #[test]
+    fn test_set_roi_format() {
+        let mut camera = Camera::with_backend(0, simulated_backend()).init().unwrap();
+        // 240 fits the sensor height but not its width
+        assert!(matches!(camera.set_roi_format(0, 0, 240, 242, 1), Err(SVBError::OverFlowResolution)));
+        assert!(matches!(camera.set_roi_format(200, 0, 160, 120, 1), Err(e) if matches!(e.root(), SVBError::OutOfBoundary)));
+        assert!(matches!(camera.set_roi_format(0, 0, 160, 120, 5), Err(SVBError::InvalidValue { kind: "bin", .. })));
+        assert!(matches!(camera.set_roi_format(0, 0, 100, 120, 1), Err(e) if matches!(e.root(), SVBError::InvalidSize)));
+        assert_eq!(camera.roi.width, 320);
+
+        let roi = camera.set_roi_format_snapped(200, 1, 100, 121, 2).unwrap();
+        assert_eq!(roi, ROIFormat { startx: 64, starty: 0, width: 96, height: 120, bin: 2 });
+        assert_eq!(camera.get_roi_format().unwrap(), roi);
+        assert_eq!(camera.roi, roi);
+    }
+
+    #[test]
+    fn test_frame_metadata() {
+        let mut camera = Camera::with_backend(0, simulated_backend()).init().unwrap();
+        camera.set_roi_format(1, 2, 160, 120, 1).unwrap();
+        camera.set_img_type(ImageType::Raw16).unwrap();
+        camera.set_flip(FlipStatus::None).unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1000, 0).unwrap();
+        camera.set_ctl_value(ControlType::Gain, 30, 0).unwrap();
+        let session = camera.start_video_capture().unwrap();
+        let first = session.get_video_frame().unwrap();
+        session.set_ctl_value(ControlType::Gain, 60, 0).unwrap();
+        session.set_flip(FlipStatus::Horizontal).unwrap();
+        let second = session.get_video_frame().unwrap();
+        session.stop().unwrap();
+
+        assert_eq!((first.width(), first.height(), first.bin()), (160, 120, 1));
+        assert_eq!(first.data.len(), 160 * 120 * 2);
+        assert_eq!(first.img_type, ImageType::Raw16);
+        assert_eq!((first.exposure, first.gain), (1000, 30));
+        assert_eq!(first.temperature, Some(25.0));
+        assert!(first.requested_at <= first.received_at);
+        assert_eq!(first.stats.dropped, 0);
+        // RGGB sensor read from an odd column
+        assert_eq!(first.bayer_pattern, Some(BayerPattern::GR));
+
+        assert_eq!((first.sequence, second.sequence), (0, 1));
+        assert_eq!(second.gain, 60);
+        // the last column of the ROI is even
+        assert_eq!(second.bayer_pattern, Some(BayerPattern::RG));
+
+        // binned pixels mix the colours of a whole cell
+        camera.set_roi_format(1, 2, 80, 60, 2).unwrap();
+        let session = camera.start_video_capture().unwrap();
+        let binned = session.get_video_frame().unwrap();
+        session.stop().unwrap();
+        assert_eq!((binned.width(), binned.bin()), (80, 2));
+        assert_eq!(binned.bayer_pattern, None);
+    }
+
+    #[test]
+    fn test_dropped_frames() {
+        let mut camera = Camera::with_backend(0, simulated_backend()).init().unwrap();
+        camera.set_roi_format(0, 0, 160, 120, 1).unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1000, 0).unwrap();
+        let session = camera.start_video_capture().unwrap();
+        let first = session.get_video_frame().unwrap();
+        // frames arrive every ms and aren't read meanwhile
+        std::thread::sleep(Duration::from_millis(50));
+        let second = session.get_video_frame().unwrap();
+        assert!(second.dropped_since(&first) > 0);
+        assert_eq!(second.stats.received, 2);
+
+        let stats = session.stats().unwrap();
+        assert_eq!((stats.received, stats.timeouts), (2, 0));
+        assert!(stats.dropped >= second.stats.dropped);
+        session.stop().unwrap();
+        // the SDK resets the count when the capture stops
+        assert_eq!(camera.dropped_frames().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_frame_buffers_are_reused() {
+        let mut camera = Camera::with_backend(0, simulated_backend()).init().unwrap();
+        camera.set_roi_format(0, 0, 160, 120, 1).unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1000, 0).unwrap();
+        let session = camera.start_video_capture().unwrap();
+        let frame = session.get_video_frame().unwrap();
+        let ptr = frame.data.as_ptr();
+        drop(frame);
+        assert_eq!(session.buffer_pool().idle(), 1);
+        assert_eq!(session.get_video_frame().unwrap().data.as_ptr(), ptr);
+
+        let mut buf = vec![0; session.frame_size()];
+        session.get_video_data(&mut buf).unwrap();
+        assert!(matches!(session.get_video_data(&mut buf[1..]), Err(SVBError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn test_snap() {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            num_stars: 20,
+            is_trigger_cam: true,
+            ..Default::default()
+        }]));
+        let mut camera = Camera::with_backend(0, backend.clone()).init().unwrap();
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let options = SnapOptions {
+            progress: Some(Box::new({
+                let statuses = statuses.clone();
+                move |progress: &SnapProgress| statuses.lock().unwrap().push(progress.status)
+            })),
+            ..Default::default()
+        };
+        let frame = camera.snap(Duration::from_millis(150), options).unwrap();
+        assert_eq!(frame.exposure, 150_000);
+        assert!(frame.received_at - frame.requested_at >= chrono::Duration::milliseconds(150));
+        // the exposure spans several polls, none of which is a timeout
+        assert!(Duration::from_millis(150) > SNAP_POLL_INTERVAL);
+        assert_eq!(frame.stats.received, 1);
+        assert_eq!(frame.stats.timeouts, 0);
+        let statuses = statuses.lock().unwrap();
+        assert_eq!(statuses.first(), Some(&ExposureStatus::Idle));
+        assert!(statuses.contains(&ExposureStatus::Working));
+        assert_eq!(statuses.last(), Some(&ExposureStatus::Success));
+        // the camera is back in video mode
+        assert_eq!(backend.get_camera_mode(0).unwrap(), libsvb::SVB_CAMERA_MODE_SVB_MODE_NORMAL);
+
+        let cancel = CancelToken::new();
+        let canceller = {
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(100));
+                cancel.cancel();
+            })
+        };
+        let options = SnapOptions {
+            cancel: Some(cancel),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let result = camera.snap(Duration::from_secs(10), options);
+        canceller.join().unwrap();
+        assert!(matches!(result, Err(SVBError::Cancelled)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // the capture has been stopped
+        camera.set_roi_format(0, 0, 160, 120, 1).unwrap();
+    }
+
+    #[test]
+    fn test_trigger_modes() {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            num_stars: 20,
+            is_trigger_cam: true,
+            ..Default::default()
+        }]));
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        assert_eq!(camera.get_supported_modes().unwrap(), CameraMode::ALL);
+        camera.set_ctl_value(ControlType::Exposure, 1000, 0).unwrap();
+        camera.set_camera_mode(CameraMode::TriggerSoft).unwrap();
+        assert_eq!(camera.get_camera_mode().unwrap(), CameraMode::TriggerSoft);
+        {
+            let session = camera.start_video_capture().unwrap();
+            // no frame without a trigger
+            assert!(matches!(session.get_video_frame(), Err(e) if matches!(e.root(), SVBError::Timeout)));
+            let frame = session.trigger_frame().unwrap();
+            assert_eq!(frame.sequence, 1);
+            assert_eq!((frame.stats.received, frame.stats.timeouts), (1, 1));
+        }
+        camera.set_camera_mode(CameraMode::Normal).unwrap();
+
+        camera
+            .set_trigger_output(TriggerOutputPin::A, true, Duration::from_millis(5), Duration::from_millis(20))
+            .unwrap();
+        let output = camera.get_trigger_output(TriggerOutputPin::A).unwrap();
+        assert_eq!((output.active_high, output.delay), (true, Duration::from_millis(5)));
+        assert!(output.is_enabled());
+        assert!(matches!(
+            camera.set_trigger_output(TriggerOutputPin::B, false, Duration::ZERO, Duration::from_secs(2001)),
+            Err(SVBError::InvalidValue { kind: "trigger output duration", .. })
+        ));
+        camera.disable_trigger_output(TriggerOutputPin::A).unwrap();
+        let output = camera.get_trigger_output(TriggerOutputPin::A).unwrap();
+        assert!(output.active_high && !output.is_enabled());
+
+        // cameras without trigger support only have video mode
+        let mut camera = Camera::with_backend(0, simulated_backend()).init().unwrap();
+        assert_eq!(camera.get_supported_modes().unwrap(), vec![CameraMode::Normal]);
+        assert!(matches!(camera.set_camera_mode(CameraMode::TriggerSoft), Err(e) if matches!(e.root(), SVBError::InvalidMode)));
+    }
+
+    #[test]
+    fn test_drop_stops_and_closes() {
+        let backend = simulated_backend();
+        let mut camera = Camera::with_backend(0, backend.clone()).init().unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1000, 0).unwrap();
+        {
+            let session = camera.start_video_capture().unwrap();
+            session.get_video_frame().unwrap();
+            assert_eq!(session.get_ctl_value(ControlType::Exposure).unwrap().value, 1000);
         }
+        // capture has been stopped, so the ROI can be changed again
+        camera.set_roi_format(0, 0, 160, 120, 1).unwrap();
+        drop(camera);
+        assert!(matches!(backend.get_roi_format(0), Err(e) if matches!(e.root(), SVBError::CameraClosed)));
+    }
+
+    #[test]
+    fn test_detached_capture() {
+        let mut camera = Camera::with_backend(0, simulated_backend()).init().unwrap();
+        camera.set_roi_format(0, 0, 160, 120, 1).unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1000, 0).unwrap();
+        let capture = camera.start_detached_capture().unwrap();
+        let frame = capture.get_video_frame().unwrap();
+        assert_eq!((frame.sequence, frame.roi.width, frame.data.len()), (0, 160, camera.frame_size()));
+        let ptr = frame.data.as_ptr();
+        drop(frame);
+        assert_eq!(capture.get_video_frame().unwrap().data.as_ptr(), ptr);
+        assert_eq!(capture.stats().received, 2);
+        // nothing stops the change, the SDK refuses it
+        assert!(matches!(camera.set_roi_format(0, 0, 320, 240, 1), Err(e) if matches!(e.root(), SVBError::VideoModeActive)));
+        drop(capture);
+        camera.set_roi_format(0, 0, 320, 240, 1).unwrap();
+    }
+
+    #[test]
+    fn test_list_cameras() {
+        let sv305 = |serial: &str| SimulatedCameraConfig {
+            friendly_name: "SVBONY SV305".to_string(),
+            serial_number: serial.to_string(),
+            max_width: 320,
+            max_height: 240,
+            ..Default::default()
+        };
+        let backend = Arc::new(SimulatedBackend::new(vec![sv305("A1"), sv305("B2")]));
+        let cameras = super::list_cameras_with(&*backend).unwrap();
+        assert_eq!(cameras.len(), 2);
+        assert_eq!(cameras[1].name, "SVBONY SV305");
+        assert_eq!(cameras[1].serial_number, "B2");
+
+        let camera = Camera::find_with(backend.clone(), |info| info.serial_number == "B2")
+            .unwrap()
+            .init()
+            .unwrap();
+        assert_eq!(camera.description().serial_number, "B2");
+        assert!(matches!(
+            Camera::find_with(backend.clone(), |info| info.name == "SVBONY SV305"),
+            Err(SVBError::AmbiguousCamera(2))
+        ));
+        assert!(matches!(
+            Camera::find_with(backend, |info| info.serial_number == "C3"),
+            Err(SVBError::CameraNotFound)
+        ));
     }
 }
@@ -1,4 +1,3 @@
-use crate::*;
 use bayer;
 use std::io::Cursor;
 extern crate image;
@@ -6,17 +5,23 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
-pub type BayerPattern = bayer::CFA;
+pub use crate::libsvb::BayerPattern;
+use crate::libsvb::SVBError;
 pub type Demosaic = bayer::Demosaic;
 pub type Depth = bayer::BayerDepth;
 pub type DebayerBuf = Vec<u8>;
-pub fn cfa_from_u32(idx: u32) -> BayerPattern {
-    match idx {
-        0 => BayerPattern::RGGB,
-        1 => BayerPattern::BGGR,
-        2 => BayerPattern::GRBG,
-        3 => BayerPattern::GBRG,
-        _ => panic!("Not exsit  bayer patten"),
+/// Bayer pattern of the `SVB_CAMERA_PROPERTY::BayerPattern` value.
+pub fn cfa_from_u32(idx: u32) -> Result<BayerPattern, SVBError> {
+    BayerPattern::try_from(idx)
+}
+impl From<BayerPattern> for bayer::CFA {
+    fn from(pattern: BayerPattern) -> bayer::CFA {
+        match pattern {
+            BayerPattern::RG => bayer::CFA::RGGB,
+            BayerPattern::BG => bayer::CFA::BGGR,
+            BayerPattern::GR => bayer::CFA::GRBG,
+            BayerPattern::GB => bayer::CFA::GBRG,
+        }
     }
 }
 #[derive(Debug, Clone)]
@@ -35,12 +40,12 @@ impl Debayer {
     }
     pub fn run_from_buf(
         &self,
-        buf: BufType,
+        buf: &[u8],
         depth: Depth,
         alg: Demosaic,
     ) -> Result<DebayerBuf, bayer::BayerError> {
         info!("Starting debayer from buffer");
-        self.run(&mut Cursor::new(&buf[..]), depth, alg)
+        self.run(&mut Cursor::new(buf), depth, alg)
     }
     pub fn run_from_file(
         &self,
@@ -59,11 +64,12 @@ impl Debayer {
         depth: Depth,
         alg: Demosaic,
     ) -> Result<DebayerBuf, bayer::BayerError> {
-        let raster_depth = match depth {
-            Depth::Depth8 => bayer::RasterDepth::Depth8,
-            _ => bayer::RasterDepth::Depth16,
+        // 3 channels of 1 or 2 bytes
+        let (raster_depth, bytes_per_pixel) = match depth {
+            Depth::Depth8 => (bayer::RasterDepth::Depth8, 3),
+            _ => (bayer::RasterDepth::Depth16, 6),
         };
-        let mut debayer_buf = vec![0; (self.width * self.height * 3) as usize];
+        let mut debayer_buf = vec![0; (self.width * self.height) as usize * bytes_per_pixel];
         let mut dst = bayer::RasterMut::new(
             self.width as usize,
             self.height as usize,
@@ -71,7 +77,7 @@ impl Debayer {
             &mut debayer_buf,
         );
         //let cfa = bayer::CFA::GRBG;
-        match bayer::run_demosaic(buf, depth, self.cfa, alg, &mut dst) {
+        match bayer::run_demosaic(buf, depth, self.cfa.into(), alg, &mut dst) {
             Ok(()) => Ok(debayer_buf),
             Err(e) => Err(e),
         }
@@ -0,0 +1,1116 @@
+use crate::debayer::{Debayer, Depth};
+use crate::frame::{Frame, ImageMetadata};
+use crate::libsvb::BayerPattern;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+mod rice;
+
+/// Error reading a FITS file or building its header. These never come from
+/// the SDK, so unlike `SVBError` they have no `SVB_ERROR_CODE`.
+#[derive(Error, Debug)]
+pub enum FitsError {
+    #[error("Failed to read {}: {source}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Invalid FITS file: {0}")]
+    InvalidFormat(String),
+
+    #[error("Invalid FITS keyword {0:?}: 1 to 8 of A-Z, 0-9, '-' and '_'")]
+    InvalidKeyword(String),
+}
+
+/// Size of the blocks which FITS headers and data units are padded to.
+pub const BLOCK_SIZE: usize = 2880;
+const CARD_SIZE: usize = 80;
+/// Longest string value which fits on a card, quotes excluded.
+const MAX_STRING_LEN: usize = 68;
+
+/// Value of a header keyword.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Logical(bool),
+    Integer(i64),
+    Real(f64),
+    String(String),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Logical(value)
+    }
+}
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Integer(value as i64)
+    }
+}
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Real(value)
+    }
+}
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl Value {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Logical(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+    /// Reals and integers.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Real(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Value of a fixed or free format value field, up to the comment.
+    fn parse(token: &str) -> Result<Option<Value>, FitsError> {
+        let value = match token {
+            "" => None,
+            "T" => Some(Value::Logical(true)),
+            "F" => Some(Value::Logical(false)),
+            _ => match token.parse::<i64>() {
+                Ok(value) => Some(Value::Integer(value)),
+                Err(_) => match token.replace('D', "E").parse::<f64>() {
+                    Ok(value) => Some(Value::Real(value)),
+                    Err(_) => return Err(FitsError::InvalidFormat(format!("unsupported value {}", token))),
+                },
+            },
+        };
+        Ok(value)
+    }
+}
+
+/// A quoted string value without the opening quote, and what follows it.
+fn parse_string(field: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = field.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '\'' {
+            value.push(c);
+        } else if field[i + 1..].starts_with('\'') {
+            chars.next();
+            value.push('\'');
+        } else {
+            return Some((value.trim_end().to_string(), &field[i + 1..]));
+        }
+    }
+    None
+}
+
+/// Fixed format value field: strings start in column 11, other values end in column 30.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Logical(value) => write!(f, "{:>20}", if *value { "T" } else { "F" }),
+            Value::Integer(value) => write!(f, "{:>20}", value),
+            Value::Real(value) => write!(f, "{:>20}", format_real(*value)),
+            Value::String(value) => {
+                let mut quoted = String::new();
+                for c in value.chars() {
+                    let escaped = match c {
+                        '\'' => "''".to_string(),
+                        ' '..='~' => c.to_string(),
+                        _ => "?".to_string(),
+                    };
+                    if quoted.len() + escaped.len() > MAX_STRING_LEN {
+                        break;
+                    }
+                    quoted.push_str(&escaped);
+                }
+                // trailing spaces are insignificant, but the value takes at least 8 characters
+                write!(f, "'{:<8}'", quoted)
+            }
+        }
+    }
+}
+
+/// Reals always get a decimal point so readers don't take them for integers,
+/// non finite values are written as undefined.
+fn format_real(value: f64) -> String {
+    if !value.is_finite() {
+        return String::new();
+    }
+    let formatted = format!("{:?}", value);
+    match formatted.split_once('e') {
+        Some((mantissa, exponent)) if mantissa.contains('.') => format!("{}E{}", mantissa, exponent),
+        Some((mantissa, exponent)) => format!("{}.0E{}", mantissa, exponent),
+        None => formatted,
+    }
+}
+
+/// One 80 character header record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    pub keyword: String,
+    /// `None` for commentary keywords like COMMENT and HISTORY.
+    pub value: Option<Value>,
+    pub comment: String,
+}
+
+impl Card {
+    fn parse(bytes: &[u8]) -> Result<Card, FitsError> {
+        let card: String = bytes
+            .iter()
+            .map(|&b| if (b' '..=b'~').contains(&b) { b as char } else { '?' })
+            .collect();
+        let keyword = card[..8].trim_end().to_string();
+        if &card[8..10] != "= " {
+            return Ok(Card {
+                keyword,
+                value: None,
+                comment: card[8..].trim_end().to_string(),
+            });
+        }
+        let field = card[10..].trim_start();
+        let (value, rest) = match field.strip_prefix('\'') {
+            Some(quoted) => match parse_string(quoted) {
+                Some((value, rest)) => (Some(Value::String(value)), rest),
+                None => {
+                    return Err(FitsError::InvalidFormat(format!("unterminated string value of {}", keyword)));
+                }
+            },
+            None => {
+                let end = field.find('/').unwrap_or(field.len());
+                (Value::parse(field[..end].trim())?, &field[end..])
+            }
+        };
+        let comment = rest.split_once('/').map(|(_, comment)| comment.trim()).unwrap_or_default();
+        Ok(Card {
+            keyword,
+            value,
+            comment: comment.to_string(),
+        })
+    }
+
+    fn to_bytes(&self) -> [u8; CARD_SIZE] {
+        let mut card = format!("{:<8}", self.keyword);
+        match &self.value {
+            Some(value) => {
+                card.push_str(&format!("= {}", value));
+                if !self.comment.is_empty() {
+                    card.push_str(" / ");
+                    card.push_str(&self.comment);
+                }
+            }
+            None => card.push_str(&self.comment),
+        }
+        let mut bytes = [b' '; CARD_SIZE];
+        for (byte, c) in bytes.iter_mut().zip(card.chars()) {
+            *byte = if (' '..='~').contains(&c) { c as u8 } else { b'?' };
+        }
+        bytes
+    }
+}
+
+/// `keyword` in upper case, if it fits in the 8 characters of a card and only
+/// has the characters FITS allows.
+fn checked_keyword(keyword: &str) -> Result<String, FitsError> {
+    let keyword = keyword.to_ascii_uppercase();
+    let valid = keyword.len() <= 8
+        && keyword
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'-' || b == b'_');
+    if !valid {
+        return Err(FitsError::InvalidKeyword(keyword));
+    }
+    Ok(keyword)
+}
+
+/// Keywords of a header data unit, in the order they are written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Header {
+    cards: Vec<Card>,
+}
+
+impl Header {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Observation keywords of `frame`, as read by PixInsight, Siril and N.I.N.A.
+    pub fn for_frame(frame: &Frame, metadata: &ImageMetadata) -> Self {
+        let mut header = Header::new();
+        let date_obs = frame.exposure_start().format("%Y-%m-%dT%H:%M:%S%.3f").to_string();
+        header.put("DATE-OBS", date_obs, "UTC start of exposure");
+        header.put("EXPTIME", frame.exposure as f64 / 1e6, "[s] exposure time");
+        header.put("INSTRUME", metadata.instrument.as_str(), "camera name");
+        header.put("GAIN", frame.gain, "sensor gain");
+        if let Some(offset) = metadata.offset {
+            header.put("OFFSET", offset, "black level offset");
+        }
+        if let Some(temperature) = frame.temperature {
+            header.put("CCD-TEMP", temperature, "[C] sensor temperature");
+        }
+        if let Some(temperature) = metadata.target_temperature {
+            header.put("SET-TEMP", temperature, "[C] cooler set point");
+        }
+        header.put("XBINNING", frame.bin(), "binning factor along x");
+        header.put("YBINNING", frame.bin(), "binning factor along y");
+        if let Some(pixel_size) = frame.pixel_size_um(metadata) {
+            // the SDK reports it as f32, further digits are only its rounding error
+            let pixel_size = (pixel_size * 1e3).round() / 1e3;
+            header.put("XPIXSZ", pixel_size, "[um] binned pixel width");
+            header.put("YPIXSZ", pixel_size, "[um] binned pixel height");
+        }
+        if let Some(geometry) = frame.geometry {
+            header.put("FOCALLEN", geometry.focal_length_mm, "[mm] focal length");
+        }
+        // the pattern is already that of the first pixel of the frame
+        if let Some(pattern) = frame.bayer_pattern {
+            header.put("BAYERPAT", pattern.to_string(), "color filter array layout");
+            header.put("XBAYROFF", 0, "bayer pattern x offset");
+            header.put("YBAYROFF", 0, "bayer pattern y offset");
+        }
+        header.put("ROWORDER", "TOP-DOWN", "first row is the top of the image");
+        header
+    }
+
+    /// Set `keyword`, replacing its value if it is already in the header.
+    ///
+    /// Fails with `FitsError::InvalidKeyword` unless the keyword has 1 to 8 of
+    /// the characters A-Z (lower case is converted), 0-9, `-` and `_`.
+    pub fn set(&mut self, keyword: &str, value: impl Into<Value>, comment: &str) -> Result<(), FitsError> {
+        let keyword = checked_keyword(keyword)?;
+        // only commentary keywords can be blank
+        if keyword.is_empty() {
+            return Err(FitsError::InvalidKeyword(keyword));
+        }
+        self.put(&keyword, value, comment);
+        Ok(())
+    }
+
+    /// `set` for the keywords of this module, which are known to be valid.
+    fn put(&mut self, keyword: &str, value: impl Into<Value>, comment: &str) {
+        let card = Card {
+            keyword: keyword.to_string(),
+            value: Some(value.into()),
+            comment: comment.to_string(),
+        };
+        match self.cards.iter_mut().find(|c| c.value.is_some() && c.keyword == card.keyword) {
+            Some(existing) => *existing = card,
+            None => self.cards.push(card),
+        }
+    }
+
+    /// Add a COMMENT or HISTORY record, failing like `set` for invalid keywords.
+    pub fn push_commentary(&mut self, keyword: &str, text: &str) -> Result<(), FitsError> {
+        self.cards.push(Card {
+            keyword: checked_keyword(keyword)?,
+            value: None,
+            comment: text.to_string(),
+        });
+        Ok(())
+    }
+
+    pub fn get(&self, keyword: &str) -> Option<&Value> {
+        self.cards
+            .iter()
+            .find(|card| card.keyword == keyword)
+            .and_then(|card| card.value.as_ref())
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    /// Value of every keyword, the first one for repeated keywords.
+    pub fn keywords(&self) -> HashMap<String, Value> {
+        let mut keywords = HashMap::new();
+        for card in &self.cards {
+            if let Some(value) = &card.value {
+                keywords.entry(card.keyword.clone()).or_insert_with(|| value.clone());
+            }
+        }
+        keywords
+    }
+
+    /// The header at the start of `bytes`, with the length of its blocks.
+    fn parse(bytes: &[u8]) -> Result<(Header, usize), FitsError> {
+        let mut header = Header::new();
+        for (i, card) in bytes.chunks_exact(CARD_SIZE).enumerate() {
+            if card[..8] == *b"END     " {
+                return Ok((header, ((i + 1) * CARD_SIZE).next_multiple_of(BLOCK_SIZE)));
+            }
+            header.cards.push(Card::parse(card)?);
+        }
+        Err(FitsError::InvalidFormat("the header has no END keyword".to_string()))
+    }
+
+    /// Append the cards, END and the padding to a whole block.
+    fn write_to(&self, out: &mut Vec<u8>) {
+        for card in &self.cards {
+            out.extend_from_slice(&card.to_bytes());
+        }
+        out.extend_from_slice(&format!("{:<80}", "END").into_bytes());
+        pad_block(out, b' ');
+    }
+}
+
+/// Keywords describing the data layout or the compression, which
+/// `to_bytes_with` writes itself.
+fn is_structural(keyword: &str) -> bool {
+    const PREFIXES: [&str; 7] = ["NAXIS", "TTYPE", "TFORM", "ZNAXIS", "ZTILE", "ZNAME", "ZVAL"];
+    matches!(
+        keyword,
+        "SIMPLE"
+            | "BITPIX"
+            | "EXTEND"
+            | "BZERO"
+            | "BSCALE"
+            | "END"
+            | "XTENSION"
+            | "PCOUNT"
+            | "GCOUNT"
+            | "TFIELDS"
+            | "THEAP"
+            | "ZIMAGE"
+            | "ZSIMPLE"
+            | "ZEXTEND"
+            | "ZBITPIX"
+            | "ZCMPTYPE"
+    ) || PREFIXES.iter().any(|prefix| keyword.starts_with(prefix))
+}
+
+fn pad_block(out: &mut Vec<u8>, fill: u8) {
+    let len = out.len().next_multiple_of(BLOCK_SIZE);
+    out.resize(len, fill);
+}
+
+/// Pixel values, stored as in a FITS data unit: x fastest, then y, then the channel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pixels {
+    U8(Vec<u8>),
+    I16(Vec<i16>),
+    /// Written as 16 bit integers with BZERO 32768.
+    U16(Vec<u16>),
+    I32(Vec<i32>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+impl Pixels {
+    pub fn bitpix(&self) -> i32 {
+        match self {
+            Pixels::U8(_) => 8,
+            Pixels::I16(_) | Pixels::U16(_) => 16,
+            Pixels::I32(_) => 32,
+            Pixels::F32(_) => -32,
+            Pixels::F64(_) => -64,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Pixels::U8(values) => values.len(),
+            Pixels::I16(values) => values.len(),
+            Pixels::U16(values) => values.len(),
+            Pixels::I32(values) => values.len(),
+            Pixels::F32(values) => values.len(),
+            Pixels::F64(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Integers as stored in the data unit, in their low `bytepix` bytes,
+    /// `None` for floats.
+    fn stored_integers(&self) -> Option<(Vec<u32>, usize)> {
+        match self {
+            Pixels::U8(values) => Some((values.iter().map(|&v| v as u32).collect(), 1)),
+            Pixels::I16(values) => Some((values.iter().map(|&v| v as u16 as u32).collect(), 2)),
+            Pixels::U16(values) => Some((values.iter().map(|&v| (v ^ 0x8000) as u32).collect(), 2)),
+            Pixels::I32(values) => Some((values.iter().map(|&v| v as u32).collect(), 4)),
+            Pixels::F32(_) | Pixels::F64(_) => None,
+        }
+    }
+
+    /// Append the values big endian.
+    fn write_to(&self, out: &mut Vec<u8>) {
+        match self {
+            Pixels::U8(values) => out.extend_from_slice(values),
+            Pixels::I16(values) => values.iter().for_each(|v| out.extend_from_slice(&v.to_be_bytes())),
+            // v - 32768, which BZERO adds back
+            Pixels::U16(values) => values.iter().for_each(|v| out.extend_from_slice(&(v ^ 0x8000).to_be_bytes())),
+            Pixels::I32(values) => values.iter().for_each(|v| out.extend_from_slice(&v.to_be_bytes())),
+            Pixels::F32(values) => values.iter().for_each(|v| out.extend_from_slice(&v.to_be_bytes())),
+            Pixels::F64(values) => values.iter().for_each(|v| out.extend_from_slice(&v.to_be_bytes())),
+        }
+    }
+}
+
+/// A 2D image, or a cube of `channels` planes for color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// 1 for mono and Bayer data, 3 for R, G and B planes.
+    pub channels: usize,
+    pub pixels: Pixels,
+}
+
+impl Image {
+    /// Pixels of `frame`: 8 or 16 bit planes for RAW and Y types, and an 8 bit
+    /// RGB cube for RGB24 and RGB32, which the SDK returns as B, G, R(, A).
+    pub fn from_frame(frame: &Frame) -> Self {
+        let (width, height) = (frame.width() as usize, frame.height() as usize);
+        let len = width * height;
+        let (channels, pixels) = match frame.img_type.bytes_per_pixel() {
+            1 => (1, Pixels::U8(frame.data[..len].to_vec())),
+            2 => (
+                1,
+                Pixels::U16(
+                    frame.data.chunks_exact(2).take(len).map(|b| u16::from_le_bytes([b[0], b[1]])).collect(),
+                ),
+            ),
+            bpp => {
+                let mut planes = vec![0; len * 3];
+                for (i, pixel) in frame.data.chunks_exact(bpp).take(len).enumerate() {
+                    planes[i] = pixel[2];
+                    planes[len + i] = pixel[1];
+                    planes[2 * len + i] = pixel[0];
+                }
+                (3, Pixels::U8(planes))
+            }
+        };
+        Image {
+            width,
+            height,
+            channels,
+            pixels,
+        }
+    }
+
+    /// A debayered image as an 8 bit RGB cube.
+    pub fn from_rgb_image(img: &image::RgbImage) -> Self {
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        let len = width * height;
+        let mut planes = vec![0; len * 3];
+        for (i, pixel) in img.pixels().enumerate() {
+            for c in 0..3 {
+                planes[c * len + i] = pixel[c];
+            }
+        }
+        Image {
+            width,
+            height,
+            channels: 3,
+            pixels: Pixels::U8(planes),
+        }
+    }
+}
+
+/// Values of a data unit, `N` bytes each.
+fn values<'a, T: 'a, const N: usize>(
+    data: &'a [u8],
+    from_be_bytes: fn([u8; N]) -> T,
+) -> impl Iterator<Item = T> + 'a {
+    data.chunks_exact(N).map(move |b| from_be_bytes(b.try_into().unwrap()))
+}
+
+impl Image {
+    /// 8 bit or little endian 16 bit data as the SDK returns it, which
+    /// `Debayer::run_from_buf` takes, `None` for other pixel types.
+    pub fn to_le_bytes(&self) -> Option<(Vec<u8>, Depth)> {
+        match &self.pixels {
+            Pixels::U8(values) => Some((values.clone(), Depth::Depth8)),
+            Pixels::U16(values) => Some((values.iter().flat_map(|v| v.to_le_bytes()).collect(), Depth::Depth16LE)),
+            _ => None,
+        }
+    }
+}
+
+/// The primary HDU of a FITS file, see `read`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FitsFile {
+    pub header: Header,
+    pub image: Image,
+}
+
+impl FitsFile {
+    /// Bayer pattern of the first pixel, from BAYERPAT shifted by XBAYROFF
+    /// and YBAYROFF.
+    pub fn bayer_pattern(&self) -> Option<BayerPattern> {
+        let name = self.header.get("BAYERPAT")?.as_str()?;
+        let pattern = BayerPattern::ALL.iter().copied().find(|p| p.to_string() == name)?;
+        let offset = |keyword| self.header.get(keyword).and_then(Value::as_f64).unwrap_or(0.0) as i32;
+        Some(pattern.shifted(offset("XBAYROFF"), offset("YBAYROFF")))
+    }
+
+    /// Debayer for the image, `None` without Bayer keywords.
+    pub fn debayer(&self) -> Option<Debayer> {
+        let pattern = self.bayer_pattern()?;
+        Some(Debayer::new(self.image.width as u32, self.image.height as u32, pattern))
+    }
+}
+
+/// Read the primary HDU of the FITS file at `path`.
+pub fn read(path: impl AsRef<Path>) -> Result<FitsFile, FitsError> {
+    let path = path.as_ref();
+    match std::fs::read(path) {
+        Ok(bytes) => from_bytes(&bytes),
+        Err(source) => Err(FitsError::Io {
+            path: path.to_path_buf(),
+            source,
+        }),
+    }
+}
+
+/// Parse a primary HDU holding a 2D image or a cube, or a RICE_1 compressed
+/// image following an empty primary HDU. Unsigned 16 bit data (BZERO 32768)
+/// comes as `Pixels::U16`, other scaled data as `Pixels::F64` of the physical
+/// values. The header of a compressed image is that of its binary table.
+pub fn from_bytes(bytes: &[u8]) -> Result<FitsFile, FitsError> {
+    let (header, data_start) = Header::parse(bytes)?;
+    if header.cards.first().map(|card| card.keyword.as_str()) != Some("SIMPLE") {
+        return Err(FitsError::InvalidFormat("it doesn't start with SIMPLE".to_string()));
+    }
+    if header.get("NAXIS").and_then(Value::as_i64) == Some(0) {
+        return from_compressed_bytes(bytes, data_start);
+    }
+    let (bitpix, width, height, channels) = layout(&header, "")?;
+    let data = bytes.get(data_start..).unwrap_or_default();
+    let len = data_len(width, height, channels, bitpix.unsigned_abs() as usize / 8, data.len())?;
+    let pixels = pixels(&header, bitpix, &data[..len])?;
+    Ok(FitsFile {
+        header,
+        image: Image {
+            width,
+            height,
+            channels,
+            pixels,
+        },
+    })
+}
+
+/// BITPIX and the axes of a 2D image or a cube, from the keywords of an image
+/// HDU or, with `prefix` "Z", of a compressed image.
+fn layout(header: &Header, prefix: &str) -> Result<(i64, usize, usize, usize), FitsError> {
+    let integer = |keyword: &str| header.get(&format!("{}{}", prefix, keyword)).and_then(Value::as_i64);
+    let axis = |n: i64| match integer(&format!("NAXIS{}", n)) {
+        Some(len) if len > 0 => Ok(len as usize),
+        len => Err(FitsError::InvalidFormat(format!("invalid axis {}NAXIS{} = {:?}", prefix, n, len))),
+    };
+    let (bitpix, naxis) = match (integer("BITPIX"), integer("NAXIS")) {
+        (Some(bitpix), Some(naxis @ 2..=3)) => (bitpix, naxis),
+        (bitpix, naxis) => {
+            return Err(FitsError::InvalidFormat(format!(
+                "unsupported image with BITPIX {:?} and NAXIS {:?}",
+                bitpix, naxis
+            )));
+        }
+    };
+    let channels = if naxis == 3 { axis(3)? } else { 1 };
+    Ok((bitpix, axis(1)?, axis(2)?, channels))
+}
+
+/// Bytes of a `width` x `height` x `channels` image of `bytepix` bytes per
+/// pixel, checked against the `available` bytes before anything is allocated.
+fn data_len(width: usize, height: usize, channels: usize, bytepix: usize, available: usize) -> Result<usize, FitsError> {
+    match width.checked_mul(height).and_then(|len| len.checked_mul(channels)).and_then(|len| len.checked_mul(bytepix)) {
+        Some(len) if len <= available => Ok(len),
+        _ => Err(FitsError::InvalidFormat(format!(
+            "a {}x{}x{} image of {} byte pixels doesn't fit in {} bytes",
+            width, height, channels, bytepix, available
+        ))),
+    }
+}
+
+/// Pixels of the big endian data unit `data`, scaled by BZERO and BSCALE.
+fn pixels(header: &Header, bitpix: i64, data: &[u8]) -> Result<Pixels, FitsError> {
+    let bzero = header.get("BZERO").and_then(Value::as_f64).unwrap_or(0.0);
+    let bscale = header.get("BSCALE").and_then(Value::as_f64).unwrap_or(1.0);
+    let scaled = bzero != 0.0 || bscale != 1.0;
+    let physical = |raw: f64| bzero + bscale * raw;
+    let pixels = match bitpix {
+        8 if scaled => Pixels::F64(data.iter().map(|&v| physical(v as f64)).collect()),
+        8 => Pixels::U8(data.to_vec()),
+        16 if bzero == 32768.0 && bscale == 1.0 => {
+            Pixels::U16(values(data, i16::from_be_bytes).map(|v| v as u16 ^ 0x8000).collect())
+        }
+        16 if scaled => Pixels::F64(values(data, i16::from_be_bytes).map(|v| physical(v as f64)).collect()),
+        16 => Pixels::I16(values(data, i16::from_be_bytes).collect()),
+        32 if scaled => Pixels::F64(values(data, i32::from_be_bytes).map(|v| physical(v as f64)).collect()),
+        32 => Pixels::I32(values(data, i32::from_be_bytes).collect()),
+        -32 if scaled => Pixels::F64(values(data, f32::from_be_bytes).map(|v| physical(v as f64)).collect()),
+        -32 => Pixels::F32(values(data, f32::from_be_bytes).collect()),
+        -64 => Pixels::F64(values(data, f64::from_be_bytes).map(physical).collect()),
+        _ => return Err(FitsError::InvalidFormat(format!("unsupported BITPIX {}", bitpix))),
+    };
+    Ok(pixels)
+}
+
+/// The compressed image in the binary table HDU at `start`.
+fn from_compressed_bytes(bytes: &[u8], start: usize) -> Result<FitsFile, FitsError> {
+    let corrupt = |what: &str| FitsError::InvalidFormat(format!("corrupt compressed image: {}", what));
+    let (header, header_len) = Header::parse(bytes.get(start..).unwrap_or_default())?;
+    let string = |keyword: &str| header.get(keyword).and_then(Value::as_str);
+    let integer = |keyword: &str| header.get(keyword).and_then(Value::as_i64);
+    if string("XTENSION") != Some("BINTABLE") || header.get("ZIMAGE").and_then(Value::as_bool) != Some(true) {
+        return Err(FitsError::InvalidFormat("it has neither an image nor a compressed image".to_string()));
+    }
+    if string("ZCMPTYPE") != Some("RICE_1") {
+        return Err(FitsError::InvalidFormat(format!(
+            "unsupported compression {:?}, only RICE_1 is supported",
+            string("ZCMPTYPE")
+        )));
+    }
+    let (bitpix, width, height, channels) = layout(&header, "Z")?;
+    let bytepix = match bitpix {
+        8 | 16 | 32 => bitpix as usize / 8,
+        _ => return Err(corrupt("RICE_1 needs integer pixels")),
+    };
+    // ZNAMEi = 'BLOCKSIZE' and its ZVALi
+    let block_size = match (1..)
+        .map_while(|i| string(&format!("ZNAME{}", i)).map(|name| (i, name)))
+        .find(|&(_, name)| name == "BLOCKSIZE")
+        .and_then(|(i, _)| integer(&format!("ZVAL{}", i)))
+    {
+        // the two sizes of the tiled image convention
+        None => rice::BLOCK_SIZE,
+        Some(size @ (16 | 32)) => size as usize,
+        Some(_) => return Err(corrupt("BLOCKSIZE isn't 16 or 32")),
+    };
+    let tile = |n: i64, default: usize| {
+        integer(&format!("ZTILE{}", n)).filter(|&len| len > 0).map_or(default, |len| len as usize)
+    };
+    let (tile_width, tile_height, tile_channels) = (tile(1, width), tile(2, 1), tile(3, 1));
+
+    // descriptors (byte count, heap offset) of the COMPRESSED_DATA column, the first one
+    let descriptor_len = match string("TFORM1") {
+        Some(form) if string("TTYPE1") == Some("COMPRESSED_DATA") && form.contains("PB") => 4,
+        Some(form) if string("TTYPE1") == Some("COMPRESSED_DATA") && form.contains("QB") => 8,
+        _ => return Err(corrupt("the first column isn't COMPRESSED_DATA")),
+    };
+    let row_len = integer("NAXIS1").unwrap_or(0).max(0) as usize;
+    let rows = integer("NAXIS2").unwrap_or(0).max(0) as usize;
+    let table = start + header_len;
+    let heap = integer("THEAP")
+        .map_or(row_len.checked_mul(rows), |theap| Some(theap.max(0) as usize))
+        .and_then(|theap| table.checked_add(theap))
+        .filter(|&heap| heap <= bytes.len())
+        .ok_or_else(|| corrupt("truncated table"))?;
+    let descriptor = |row: usize, field: usize| {
+        let field = row
+            .checked_mul(row_len)
+            .and_then(|at| at.checked_add(table + field * descriptor_len))
+            .and_then(|at| bytes.get(at..at.checked_add(descriptor_len)?))
+            .ok_or_else(|| corrupt("truncated table"))?;
+        Ok(field.iter().fold(0usize, |value, &b| (value << 8) | b as usize))
+    };
+
+    // even a heap of unchanged values holds only so many pixels
+    let available = rice::max_values(bytes.len() - heap, bytepix, block_size).saturating_mul(bytepix);
+    let mut data = vec![0; data_len(width, height, channels, bytepix, available)?];
+    let mut row = 0;
+    for z0 in (0..channels).step_by(tile_channels) {
+        for y0 in (0..height).step_by(tile_height) {
+            for x0 in (0..width).step_by(tile_width) {
+                if row >= rows {
+                    return Err(corrupt("fewer tiles than the image needs"));
+                }
+                let (len, offset) = (descriptor(row, 0)?, descriptor(row, 1)?);
+                let compressed = heap
+                    .checked_add(offset)
+                    .and_then(|at| bytes.get(at..at.checked_add(len)?))
+                    .ok_or_else(|| corrupt("truncated heap"))?;
+                let (tw, th, tc) = (
+                    tile_width.min(width - x0),
+                    tile_height.min(height - y0),
+                    tile_channels.min(channels - z0),
+                );
+                let values = rice::decompress(compressed, tw * th * tc, bytepix, block_size)
+                    .ok_or_else(|| corrupt("invalid RICE_1 data"))?;
+                let mut values = values.into_iter();
+                for z in z0..z0 + tc {
+                    for y in y0..y0 + th {
+                        for x in x0..x0 + tw {
+                            let at = ((z * height + y) * width + x) * bytepix;
+                            let value = values.next().unwrap_or_default().to_be_bytes();
+                            data[at..at + bytepix].copy_from_slice(&value[4 - bytepix..]);
+                        }
+                    }
+                }
+                row += 1;
+            }
+        }
+    }
+    let pixels = pixels(&header, bitpix, &data)?;
+    Ok(FitsFile {
+        header,
+        image: Image {
+            width,
+            height,
+            channels,
+            pixels,
+        },
+    })
+}
+
+/// How `to_bytes_with` stores the image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// Tile compression with RICE_1 (the ZIMAGE convention of fpack), one
+    /// tile per row, for integer pixels; float pixels are written uncompressed.
+    Rice,
+}
+
+/// Set the BITPIX and NAXIS keywords of `image`, or ZBITPIX and ZNAXIS with `prefix` "Z".
+fn set_layout(header: &mut Header, image: &Image, prefix: &str) {
+    let keyword = |name: &str| format!("{}{}", prefix, name);
+    header.put(&keyword("BITPIX"), image.pixels.bitpix(), "bits per data value");
+    header.put(&keyword("NAXIS"), if image.channels > 1 { 3 } else { 2 }, "number of data axes");
+    header.put(&keyword("NAXIS1"), image.width as i64, "length of data axis 1");
+    header.put(&keyword("NAXIS2"), image.height as i64, "length of data axis 2");
+    if image.channels > 1 {
+        header.put(&keyword("NAXIS3"), image.channels as i64, "length of data axis 3");
+    }
+}
+
+/// Add BZERO for unsigned data and the cards of `header`.
+fn extend_with(target: &mut Header, header: &Header, image: &Image) {
+    if let Pixels::U16(_) = image.pixels {
+        target.put("BZERO", 32768, "offset data range to that of unsigned short");
+        target.put("BSCALE", 1, "default scaling factor");
+    }
+    target
+        .cards
+        .extend(header.cards.iter().filter(|card| !is_structural(&card.keyword)).cloned());
+}
+
+/// A primary HDU holding `image`, with the layout keywords followed by the
+/// cards of `header`.
+pub fn to_bytes(header: &Header, image: &Image) -> Vec<u8> {
+    to_bytes_with(header, image, Compression::None)
+}
+
+/// Like `to_bytes`, storing the image as `compression` tells.
+pub fn to_bytes_with(header: &Header, image: &Image, compression: Compression) -> Vec<u8> {
+    let stored = match compression {
+        Compression::None => None,
+        Compression::Rice => {
+            let stored = image.pixels.stored_integers();
+            if stored.is_none() {
+                warn!("RICE_1 only compresses integers, writing BITPIX {} uncompressed", image.pixels.bitpix());
+            }
+            stored
+        }
+    };
+    if let Some((values, bytepix)) = stored {
+        return to_compressed_bytes(header, image, &values, bytepix);
+    }
+
+    let mut primary = Header::new();
+    primary.put("SIMPLE", true, "conforms to FITS standard");
+    set_layout(&mut primary, image, "");
+    extend_with(&mut primary, header, image);
+
+    let data_len = image.pixels.len() * image.pixels.bitpix().unsigned_abs() as usize / 8;
+    let mut fits = Vec::with_capacity(BLOCK_SIZE + (data_len + BLOCK_SIZE) / BLOCK_SIZE * BLOCK_SIZE);
+    primary.write_to(&mut fits);
+    image.pixels.write_to(&mut fits);
+    pad_block(&mut fits, 0);
+    fits
+}
+
+/// An empty primary HDU followed by a binary table with a RICE_1 compressed
+/// row of `values` in each row.
+fn to_compressed_bytes(header: &Header, image: &Image, values: &[u32], bytepix: usize) -> Vec<u8> {
+    let mut primary = Header::new();
+    primary.put("SIMPLE", true, "conforms to FITS standard");
+    primary.put("BITPIX", 8, "bits per data value");
+    primary.put("NAXIS", 0, "the image is in the extension");
+    primary.put("EXTEND", true, "file may contain extensions");
+
+    let tiles: Vec<Vec<u8>> = values.chunks(image.width.max(1)).map(|row| rice::compress(row, bytepix)).collect();
+    let heap_len: usize = tiles.iter().map(Vec::len).sum();
+    let max_len = tiles.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut table = Header::new();
+    table.put("XTENSION", "BINTABLE", "binary table extension");
+    table.put("BITPIX", 8, "8-bit bytes");
+    table.put("NAXIS", 2, "2-dimensional binary table");
+    table.put("NAXIS1", 8, "width of table in bytes");
+    table.put("NAXIS2", tiles.len() as i64, "number of rows in table");
+    table.put("PCOUNT", heap_len as i64, "size of special data area");
+    table.put("GCOUNT", 1, "one data group");
+    table.put("TFIELDS", 1, "number of fields in each row");
+    table.put("TTYPE1", "COMPRESSED_DATA", "label for field 1");
+    table.put("TFORM1", format!("1PB({})", max_len), "data format of field: variable length array");
+    table.put("ZIMAGE", true, "extension contains compressed image");
+    set_layout(&mut table, image, "Z");
+    table.put("ZTILE1", image.width as i64, "size of tiles to be compressed");
+    table.put("ZTILE2", 1, "size of tiles to be compressed");
+    if image.channels > 1 {
+        table.put("ZTILE3", 1, "size of tiles to be compressed");
+    }
+    table.put("ZCMPTYPE", "RICE_1", "compression algorithm");
+    table.put("ZNAME1", "BLOCKSIZE", "compression block size");
+    table.put("ZVAL1", rice::BLOCK_SIZE as i64, "pixels per block");
+    table.put("ZNAME2", "BYTEPIX", "bytes per pixel (1, 2, 4, or 8)");
+    table.put("ZVAL2", bytepix as i64, "bytes per pixel (1, 2, 4, or 8)");
+    extend_with(&mut table, header, image);
+
+    let mut fits = Vec::with_capacity(3 * BLOCK_SIZE + tiles.len() * 8 + heap_len);
+    primary.write_to(&mut fits);
+    table.write_to(&mut fits);
+    let mut offset = 0;
+    for tile in &tiles {
+        fits.extend_from_slice(&(tile.len() as i32).to_be_bytes());
+        fits.extend_from_slice(&(offset as i32).to_be_bytes());
+        offset += tile.len();
+    }
+    for tile in &tiles {
+        fits.extend_from_slice(tile);
+    }
+    pad_block(&mut fits, 0);
+    fits
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_bytes, read, to_bytes, to_bytes_with, Compression, FitsError, Header, Image, Pixels, Value, BLOCK_SIZE};
+    use crate::debayer::Demosaic;
+    use crate::camera::Camera;
+    use crate::libsvb::{ControlType, ImageType};
+    use crate::simulator::{SimulatedBackend, SimulatedCameraConfig};
+
+    use std::io;
+    use std::sync::Arc;
+
+    fn card(fits: &[u8], keyword: &str) -> String {
+        fits[..BLOCK_SIZE]
+            .chunks(80)
+            .map(|card| String::from_utf8_lossy(card).to_string())
+            .find(|card| card[..8].trim_end() == keyword)
+            .unwrap_or_else(|| panic!("no {} card", keyword))
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            is_color: true,
+            num_stars: 20,
+            ..Default::default()
+        }]));
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1500, 0).unwrap();
+        camera.set_img_type(ImageType::Raw16).unwrap();
+        let frame = camera.start_video_capture().unwrap().get_video_frame().unwrap();
+        let metadata = camera.image_metadata().unwrap();
+        let header = Header::for_frame(&frame, &metadata);
+        let fits = to_bytes(&header, &Image::from_frame(&frame));
+
+        assert_eq!(fits.len() % BLOCK_SIZE, 0);
+        assert_eq!(&fits[..30], b"SIMPLE  =                    T");
+        assert_eq!(card(&fits, "BITPIX")[..30], format!("BITPIX  = {:>20}", 16));
+        assert_eq!(card(&fits, "BZERO")[..30], format!("BZERO   = {:>20}", 32768));
+        assert_eq!(card(&fits, "NAXIS1")[..30], format!("NAXIS1  = {:>20}", 320));
+        assert_eq!(card(&fits, "EXPTIME")[..30], format!("EXPTIME = {:>20}", "0.0015"));
+        let pattern = frame.bayer_pattern.unwrap();
+        assert_eq!(card(&fits, "BAYERPAT")[..20], format!("BAYERPAT= '{:<8}'", pattern.to_string()));
+        assert!(card(&fits, "INSTRUME").contains(&format!("'{}", metadata.instrument)));
+        assert_eq!(header.get("ROWORDER"), Some(&Value::from("TOP-DOWN")));
+
+        // first pixel big endian, shifted by BZERO
+        let data = &fits[fits.len() - (320 * 240 * 2usize).next_multiple_of(BLOCK_SIZE)..];
+        let first = u16::from_le_bytes([frame.data[0], frame.data[1]]);
+        assert_eq!(i16::from_be_bytes([data[0], data[1]]) as i32 + 32768, first as i32);
+
+        let rgb = Image::from_rgb_image(&image::RgbImage::from_fn(4, 2, |x, _| image::Rgb([x as u8, 10, 20])));
+        assert_eq!(rgb.pixels, Pixels::U8([vec![0, 1, 2, 3, 0, 1, 2, 3], vec![10; 8], vec![20; 8]].concat()));
+        let fits = to_bytes(&Header::new(), &rgb);
+        assert_eq!(card(&fits, "NAXIS")[..30], format!("NAXIS   = {:>20}", 3));
+        assert_eq!(card(&fits, "NAXIS3")[..30], format!("NAXIS3  = {:>20}", 3));
+        assert_eq!(fits.len(), 2 * BLOCK_SIZE);
+    }
+
+    #[test]
+    fn test_values() {
+        assert_eq!(Value::Real(2.0).to_string().trim(), "2.0");
+        assert_eq!(Value::Real(1e-7).to_string().trim(), "1.0E-7");
+        assert_eq!(Value::from("it's").to_string(), "'it''s   '");
+        assert_eq!(Value::from("x".repeat(80)).to_string().len(), 70);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            is_color: true,
+            num_stars: 20,
+            ..Default::default()
+        }]));
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1500, 0).unwrap();
+        camera.set_img_type(ImageType::Raw16).unwrap();
+        // odd start, so the pattern differs from the sensor's
+        camera.set_roi_format(1, 1, 160, 120, 1).unwrap();
+        let frame = camera.start_video_capture().unwrap().get_video_frame().unwrap();
+        let mut header = Header::for_frame(&frame, &camera.image_metadata().unwrap());
+        header.set("OBJECT", "M 31 'Andromeda'", "target").unwrap();
+        header.push_commentary("HISTORY", "captured by the simulator").unwrap();
+        for keyword in ["EXPOSURE1", "DATE OBS", "", "CCD.TEMP"] {
+            assert!(matches!(header.set(keyword, 1, ""), Err(FitsError::InvalidKeyword(_))));
+        }
+        let fits = from_bytes(&to_bytes(&header, &Image::from_frame(&frame))).unwrap();
+
+        assert_eq!(fits.image, Image::from_frame(&frame));
+        let keywords = fits.header.keywords();
+        assert_eq!(keywords["EXPTIME"], Value::Real(0.0015));
+        assert_eq!(keywords["GAIN"], Value::Integer(frame.gain));
+        assert_eq!(keywords["OBJECT"], Value::from("M 31 'Andromeda'"));
+        assert_eq!(keywords["BZERO"], Value::Integer(32768));
+        assert_eq!(fits.header.cards().last().unwrap().comment, "captured by the simulator");
+        assert_eq!(fits.bayer_pattern(), frame.bayer_pattern);
+        let (buf, depth) = fits.image.to_le_bytes().unwrap();
+        assert_eq!(buf, &frame.data[..]);
+        assert!(fits.debayer().unwrap().run_from_buf(&buf, depth, Demosaic::Linear).is_ok());
+
+        for pixels in [
+            Pixels::U8(vec![0, 1, 255, 7]),
+            Pixels::I16(vec![-32768, -1, 0, 32767]),
+            Pixels::I32(vec![i32::MIN, -1, 0, i32::MAX]),
+            Pixels::F32(vec![-1.5, 0.0, 0.25, 1e30]),
+            Pixels::F64(vec![-1.5, 0.0, 0.25, 1e300]),
+        ] {
+            let image = Image {
+                width: 2,
+                height: 2,
+                channels: 1,
+                pixels,
+            };
+            assert_eq!(from_bytes(&to_bytes(&Header::new(), &image)).unwrap().image, image);
+        }
+
+        // scaled data comes as physical values
+        let mut bytes = to_bytes(
+            &Header::new(),
+            &Image {
+                width: 2,
+                height: 1,
+                channels: 1,
+                pixels: Pixels::I16(vec![1, 2]),
+            },
+        );
+        // in place of END, which moves to the next card
+        let cards = format!("{:<80}{:<80}", format!("BSCALE  = {:>20}", "0.5"), "END");
+        bytes[5 * 80..7 * 80].copy_from_slice(cards.as_bytes());
+        assert_eq!(from_bytes(&bytes).unwrap().image.pixels, Pixels::F64(vec![0.5, 1.0]));
+        assert!(matches!(from_bytes(&bytes[..BLOCK_SIZE + 2]), Err(FitsError::InvalidFormat(_))));
+        assert!(matches!(read("/nonexistent.fits"), Err(FitsError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound));
+    }
+
+    /// `bytes` with the value of the first `keyword` card replaced.
+    fn with_card(bytes: &[u8], keyword: &str, value: i64) -> Vec<u8> {
+        let mut bytes = bytes.to_vec();
+        let at = (0..bytes.len()).step_by(80).find(|&at| bytes[at..at + 8] == *format!("{:<8}", keyword).as_bytes()).unwrap();
+        bytes[at..at + 80].copy_from_slice(format!("{:<80}", format!("{:<8}= {:>20}", keyword, value)).as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_malformed_sizes() {
+        let image = Image {
+            width: 2,
+            height: 1,
+            channels: 1,
+            pixels: Pixels::I16(vec![1, 2]),
+        };
+        let bytes = to_bytes(&Header::new(), &image);
+        // larger than the data unit, and too large for usize
+        for naxis1 in [100_000, 1 << 62] {
+            assert!(matches!(from_bytes(&with_card(&bytes, "NAXIS1", naxis1)), Err(FitsError::InvalidFormat(_))));
+        }
+
+        let compressed = to_bytes_with(&Header::new(), &image, Compression::Rice);
+        // 20 GB which the heap can't hold, refused before allocating
+        let huge = with_card(&with_card(&compressed, "ZNAXIS1", 100_000), "ZNAXIS2", 100_000);
+        assert!(matches!(from_bytes(&huge), Err(FitsError::InvalidFormat(_))));
+        assert!(matches!(from_bytes(&with_card(&compressed, "ZNAXIS1", 1 << 62)), Err(FitsError::InvalidFormat(_))));
+        // table rows which overflow or run past the end of the file
+        for naxis1 in [1 << 62, 100_000] {
+            assert!(matches!(from_bytes(&with_card(&compressed, "NAXIS1", naxis1)), Err(FitsError::InvalidFormat(_))));
+        }
+    }
+
+    #[test]
+    fn test_rice_compression() {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            num_stars: 20,
+            ..Default::default()
+        }]));
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1500, 0).unwrap();
+        camera.set_img_type(ImageType::Raw16).unwrap();
+        let frame = camera.start_video_capture().unwrap().get_video_frame().unwrap();
+        let header = Header::for_frame(&frame, &camera.image_metadata().unwrap());
+        let image = Image::from_frame(&frame);
+
+        let fits = to_bytes_with(&header, &image, Compression::Rice);
+        assert!(fits.len() < to_bytes(&header, &image).len() / 2);
+        assert_eq!(&fits[BLOCK_SIZE..BLOCK_SIZE + 20], b"XTENSION= 'BINTABLE'");
+        let read = from_bytes(&fits).unwrap();
+        assert_eq!(read.image, image);
+        assert_eq!(read.header.get("ZCMPTYPE"), Some(&Value::from("RICE_1")));
+        assert_eq!(read.header.get("EXPTIME"), header.get("EXPTIME"));
+        // rewriting what was read gives the same file
+        assert_eq!(to_bytes_with(&read.header, &read.image, Compression::Rice), fits);
+
+        let cube = Image::from_rgb_image(&image::RgbImage::from_fn(5, 3, |x, y| image::Rgb([x as u8, y as u8, 200])));
+        let read = from_bytes(&to_bytes_with(&Header::new(), &cube, Compression::Rice)).unwrap();
+        assert_eq!(read.image, cube);
+        // floats can't be RICE_1 compressed
+        let floats = Image {
+            width: 2,
+            height: 1,
+            channels: 1,
+            pixels: Pixels::F32(vec![0.5, 1.5]),
+        };
+        assert_eq!(to_bytes_with(&Header::new(), &floats, Compression::Rice), to_bytes(&Header::new(), &floats));
+    }
+}
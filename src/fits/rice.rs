@@ -0,0 +1,189 @@
+//! RICE_1 compression of the tiles of a compressed image, bit compatible
+//! with `fits_rcomp` and `fits_rdecomp` of cfitsio.
+
+/// Pixels per block sharing a split position, the BLOCKSIZE fpack uses.
+pub(super) const BLOCK_SIZE: usize = 32;
+
+/// Bits of the split position code, largest split position and bits per
+/// value for values of `bytepix` bytes.
+fn params(bytepix: usize) -> (u32, u32, u32) {
+    match bytepix {
+        1 => (3, 6, 8),
+        2 => (4, 14, 16),
+        _ => (5, 25, 32),
+    }
+}
+
+/// Most values `len` compressed bytes of `bytepix` values can hold: a block
+/// of `block_size` unchanged values takes only its split position code.
+pub(super) fn max_values(len: usize, bytepix: usize, block_size: usize) -> usize {
+    let (fsbits, _, _) = params(bytepix);
+    (len.saturating_mul(8) / fsbits as usize).saturating_mul(block_size)
+}
+
+fn mask(bits: u32) -> u32 {
+    ((1u64 << bits) - 1) as u32
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Append the low `n` bits of `value`, `n` at most 32.
+    fn write(&mut self, value: u32, n: u32) {
+        self.buffer = (self.buffer << n) | (value & mask(n)) as u64;
+        self.bits += n;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.buffer >> self.bits) as u8);
+        }
+        self.buffer &= mask(self.bits) as u64;
+    }
+
+    /// `zeros` 0 bits followed by a 1 bit.
+    fn write_unary(&mut self, mut zeros: u32) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros + 1);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push((self.buffer << (8 - self.bits)) as u8);
+        }
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Option<u32> {
+        let byte = *self.bytes.get(self.pos / 8)?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Some(bit as u32)
+    }
+
+    fn read(&mut self, n: u32) -> Option<u32> {
+        (0..n).try_fold(0u32, |value, _| Some((value << 1) | self.bit()?))
+    }
+
+    fn read_unary(&mut self) -> Option<u32> {
+        let mut zeros = 0;
+        while self.bit()? == 0 {
+            zeros += 1;
+        }
+        Some(zeros)
+    }
+}
+
+/// Compress the stored integers of a tile, given as their `bytepix` low bytes.
+pub(super) fn compress(values: &[u32], bytepix: usize) -> Vec<u8> {
+    let (fsbits, fsmax, bbits) = params(bytepix);
+    let mut out = BitWriter {
+        bytes: Vec::with_capacity(values.len() * bytepix / 2),
+        buffer: 0,
+        bits: 0,
+    };
+    let Some(&first) = values.first() else {
+        return out.finish();
+    };
+    out.write(first, bbits);
+
+    let mut last = first;
+    let mut diffs = [0u32; BLOCK_SIZE];
+    for block in values.chunks(BLOCK_SIZE) {
+        // differences wrapped to the value size, mapped to positive numbers
+        let mut sum = 0.0;
+        for (diff, &value) in diffs.iter_mut().zip(block) {
+            let shift = 32 - bbits;
+            let d = (((value.wrapping_sub(last) << shift) as i32) >> shift) as i64;
+            *diff = ((d << 1) ^ (d >> 63)) as u32;
+            sum += *diff as f64;
+            last = value;
+        }
+        let diffs = &diffs[..block.len()];
+
+        let len = block.len() as f64;
+        let mean = ((sum - (block.len() / 2) as f64 - 1.0) / len).max(0.0);
+        let fs = u32::BITS - ((mean as u32) >> 1).leading_zeros();
+        if fs >= fsmax {
+            // high entropy, the differences are stored as they are
+            out.write(fsmax + 1, fsbits);
+            for &diff in diffs {
+                out.write(diff, bbits);
+            }
+        } else if fs == 0 && sum == 0.0 {
+            out.write(0, fsbits);
+        } else {
+            out.write(fs + 1, fsbits);
+            for &diff in diffs {
+                out.write_unary(diff >> fs);
+                if fs > 0 {
+                    out.write(diff & mask(fs), fs);
+                }
+            }
+        }
+    }
+    out.finish()
+}
+
+/// The `len` stored integers of a tile compressed with `block_size` pixels
+/// per block, `None` if the data is corrupt.
+pub(super) fn decompress(bytes: &[u8], len: usize, bytepix: usize, block_size: usize) -> Option<Vec<u32>> {
+    let (fsbits, fsmax, bbits) = params(bytepix);
+    let mut input = BitReader { bytes, pos: 0 };
+    let mut values = Vec::with_capacity(len);
+    let mut last = input.read(bbits)?;
+    while values.len() < len {
+        let block = block_size.max(1).min(len - values.len());
+        match input.read(fsbits)?.checked_sub(1) {
+            None => values.extend(std::iter::repeat_n(last, block)),
+            Some(fs) => {
+                for _ in 0..block {
+                    let diff = match fs {
+                        fs if fs == fsmax => input.read(bbits)?,
+                        0 => input.read_unary()?,
+                        fs if fs < fsmax => (input.read_unary()? << fs) | input.read(fs)?,
+                        _ => return None,
+                    };
+                    let d = (diff >> 1) ^ (diff & 1).wrapping_neg();
+                    last = last.wrapping_add(d) & mask(bbits);
+                    values.push(last);
+                }
+            }
+        }
+    }
+    Some(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compress, decompress, BLOCK_SIZE};
+
+    #[test]
+    fn test_rice() {
+        // first value raw, then split position 0: 1 in 4 bits and the
+        // differences 0, 2 and 4 (+1, +2 mapped) in unary
+        assert_eq!(compress(&[100, 101, 103], 2), [0x00, 0x64, 0x19, 0x08]);
+
+        let flat = vec![7; 40];
+        let noisy: Vec<u32> = (0..100u32).map(|i| i.wrapping_mul(2_654_435_761) & 0xffff).collect();
+        let signed: Vec<u32> = [0, i32::MIN, -1, i32::MAX, 5].iter().map(|&v| v as u32).collect();
+        for (values, bytepix) in [(flat, 2), (noisy, 2), (signed, 4), (vec![0, 255, 128, 3], 1)] {
+            let compressed = compress(&values, bytepix);
+            assert_eq!(decompress(&compressed, values.len(), bytepix, BLOCK_SIZE).unwrap(), values);
+        }
+        assert_eq!(compress(&[7; 40], 2).len(), 3);
+        assert!(decompress(&[0x00], 2, 2, BLOCK_SIZE).is_none());
+    }
+}
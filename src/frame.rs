@@ -0,0 +1,108 @@
+use crate::libsvb::{BayerPattern, FlipStatus, ImageType, ROIFormat};
+use crate::optics::ImageGeometry;
+use crate::pool::PooledBuffer;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// A video frame together with the camera settings it was captured with.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Pixel data as returned by the SDK (little endian for 16 bit types),
+    /// given back to the camera's buffer pool when the frame is dropped.
+    pub data: PooledBuffer,
+    /// ROI of the frame, in binned pixels.
+    pub roi: ROIFormat,
+    pub img_type: ImageType,
+    /// Bayer pattern of the top left pixel of this frame, `None` for mono
+    /// cameras, debayered image types and binned frames.
+    pub bayer_pattern: Option<BayerPattern>,
+    /// Exposure in µs.
+    pub exposure: i64,
+    pub gain: i64,
+    /// Sensor temperature in °C, if the camera reports it.
+    pub temperature: Option<f64>,
+    /// Number of the frame within its capture session, starting at 0.
+    pub sequence: u64,
+    /// When the frame was requested from the SDK.
+    pub requested_at: DateTime<Utc>,
+    /// When the SDK returned the frame.
+    pub received_at: DateTime<Utc>,
+    /// Counters of the capture, including this frame, and the frames the SDK
+    /// dropped since the capture was started.
+    pub stats: CaptureStats,
+    /// Plate scale and field of view, if the camera's optics are set.
+    pub geometry: Option<ImageGeometry>,
+}
+
+/// Camera state written to image file headers next to what a `Frame`
+/// records, see `Camera::image_metadata`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageMetadata {
+    /// Camera name.
+    pub instrument: String,
+    /// Black level offset, if the camera has one.
+    pub offset: Option<i64>,
+    /// Cooler set point in °C, for cooled cameras.
+    pub target_temperature: Option<f64>,
+    /// Unbinned pixel size in µm.
+    pub pixel_size_um: Option<f64>,
+}
+
+/// Counters of a video capture, see `CaptureSession::stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Frames read from the SDK.
+    pub received: u64,
+    /// Reads which timed out.
+    pub timeouts: u64,
+    /// Frames the SDK dropped because they weren't read in time.
+    pub dropped: u64,
+}
+
+impl Frame {
+    /// Frames the SDK dropped between `previous` and this frame of the same capture.
+    pub fn dropped_since(&self, previous: &Frame) -> u64 {
+        self.stats.dropped.saturating_sub(previous.stats.dropped)
+    }
+    /// Estimated start of the exposure: `exposure` before the frame was
+    /// received, but not before it was requested.
+    pub fn exposure_start(&self) -> DateTime<Utc> {
+        self.requested_at
+            .max(self.received_at - Duration::microseconds(self.exposure))
+    }
+    /// Binned pixel size in µm, from the optics or from `metadata`.
+    pub fn pixel_size_um(&self, metadata: &ImageMetadata) -> Option<f64> {
+        match self.geometry {
+            Some(geometry) => Some(geometry.pixel_size_um),
+            None => metadata.pixel_size_um.map(|size| size * self.bin().max(1) as f64),
+        }
+    }
+    pub fn width(&self) -> u32 {
+        self.roi.width as u32
+    }
+    pub fn height(&self) -> u32 {
+        self.roi.height as u32
+    }
+    pub fn bin(&self) -> i32 {
+        self.roi.bin
+    }
+}
+
+/// Bayer pattern of the first pixel of a frame captured with `roi` and `flip`
+/// from a sensor whose top left pixel has the pattern `sensor`, `None` for
+/// binned frames whose pixels sum a whole colour cell.
+pub fn frame_bayer_pattern(sensor: BayerPattern, roi: &ROIFormat, flip: FlipStatus) -> Option<BayerPattern> {
+    if roi.bin > 1 {
+        return None;
+    }
+    // first pixel of the frame on the sensor
+    let mut dx = roi.startx;
+    let mut dy = roi.starty;
+    if matches!(flip, FlipStatus::Horizontal | FlipStatus::Both) {
+        dx += roi.width - 1;
+    }
+    if matches!(flip, FlipStatus::Vertical | FlipStatus::Both) {
+        dy += roi.height - 1;
+    }
+    Some(sensor.shifted(dx, dy))
+}
@@ -0,0 +1,205 @@
+use crate::backend::Backend;
+use crate::libsvb::{GuideDirection, SVBError};
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Mount axis moved by a guide pulse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GuideAxis {
+    /// Moved by east and west pulses.
+    Ra,
+    /// Moved by north and south pulses.
+    Dec,
+}
+
+impl GuideDirection {
+    pub fn axis(self) -> GuideAxis {
+        match self {
+            GuideDirection::North | GuideDirection::South => GuideAxis::Dec,
+            GuideDirection::East | GuideDirection::West => GuideAxis::Ra,
+        }
+    }
+}
+
+/// Pulse started by `Camera::start_pulse_guide`.
+///
+/// Dropping it doesn't stop the pulse; its axis stays busy until the pulse is over.
+#[derive(Debug)]
+pub struct GuidePulse {
+    direction: GuideDirection,
+    duration: Duration,
+    started_at: Instant,
+    thread: JoinHandle<Result<(), SVBError>>,
+}
+
+impl GuidePulse {
+    pub fn direction(&self) -> GuideDirection {
+        self.direction
+    }
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+    /// When the pulse is over.
+    pub fn finishes_at(&self) -> Instant {
+        self.started_at + self.duration
+    }
+    pub fn remaining(&self) -> Duration {
+        self.finishes_at().saturating_duration_since(Instant::now())
+    }
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+    /// Block until the pulse is over, returning the error of the SDK call.
+    pub fn wait(self) -> Result<(), SVBError> {
+        self.thread.join().unwrap_or_else(|_| {
+            error!("Thread of the {} pulse panicked", self.direction);
+            Err(SVBError::GeneralError)
+        })
+    }
+}
+
+/// Direction of the pulse running on each axis, shared by a camera and the
+/// threads of its pulses.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ActivePulses(Arc<Mutex<[Option<GuideDirection>; 2]>>);
+
+impl ActivePulses {
+    /// Send a pulse and return once it is over.
+    pub(crate) fn run(
+        &self,
+        backend: &dyn Backend,
+        camera_id: i32,
+        direction: GuideDirection,
+        duration: Duration,
+    ) -> Result<(), SVBError> {
+        let duration_ms = pulse_duration_ms(duration)?;
+        let _claim = self.claim(direction)?;
+        send_pulse(backend, camera_id, direction, duration_ms, Instant::now())
+    }
+
+    /// Send a pulse from a new thread.
+    pub(crate) fn start(
+        &self,
+        backend: Arc<dyn Backend>,
+        camera_id: i32,
+        direction: GuideDirection,
+        duration: Duration,
+    ) -> Result<GuidePulse, SVBError> {
+        let duration_ms = pulse_duration_ms(duration)?;
+        let claim = self.claim(direction)?;
+        let started_at = Instant::now();
+        let thread = thread::Builder::new()
+            .name(format!("svb-guide-{}", camera_id))
+            .spawn(move || {
+                let _claim = claim;
+                send_pulse(&*backend, camera_id, direction, duration_ms, started_at)
+            })
+            .map_err(|e| {
+                error!("{} : Failed to spawn guide thread", e);
+                SVBError::GeneralError
+            })?;
+        Ok(GuidePulse {
+            direction,
+            duration: Duration::from_millis(duration_ms as u64),
+            started_at,
+            thread,
+        })
+    }
+
+    /// Reserve the axis of `direction` until the returned claim is dropped.
+    fn claim(&self, direction: GuideDirection) -> Result<AxisClaim, SVBError> {
+        let axis = direction.axis();
+        let mut active = self.0.lock().unwrap();
+        if let Some(running) = active[axis as usize] {
+            error!("Refused {} pulse, a {} pulse is still running", direction, running);
+            return Err(SVBError::PulseGuideInProgress(running));
+        }
+        active[axis as usize] = Some(direction);
+        Ok(AxisClaim {
+            pulses: self.clone(),
+            axis,
+        })
+    }
+}
+
+struct AxisClaim {
+    pulses: ActivePulses,
+    axis: GuideAxis,
+}
+
+impl Drop for AxisClaim {
+    fn drop(&mut self) {
+        self.pulses.0.lock().unwrap()[self.axis as usize] = None;
+    }
+}
+
+/// `duration` in the whole ms the SDK takes.
+fn pulse_duration_ms(duration: Duration) -> Result<i32, SVBError> {
+    match i32::try_from(duration.as_millis()) {
+        Ok(ms) if ms > 0 => Ok(ms),
+        _ => {
+            error!("pulse duration {:?} is out of range", duration);
+            Err(SVBError::InvalidValue {
+                kind: "pulse duration",
+                value: duration.as_millis() as i64,
+            })
+        }
+    }
+}
+
+fn send_pulse(
+    backend: &dyn Backend,
+    camera_id: i32,
+    direction: GuideDirection,
+    duration_ms: i32,
+    started_at: Instant,
+) -> Result<(), SVBError> {
+    backend.pulse_guide(camera_id, direction.into(), duration_ms)?;
+    // the axis is busy until the pulse is over, even if the SDK returned earlier
+    let end = started_at + Duration::from_millis(duration_ms as u64);
+    thread::sleep(end.saturating_duration_since(Instant::now()));
+    debug!("Pulse guided {} for {} ms on camera_id {}", direction, duration_ms, camera_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::camera::Camera;
+    use crate::libsvb::{GuideDirection, SVBError};
+    use crate::simulator::{SimulatedBackend, SimulatedCameraConfig};
+
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_overlapping_pulses() {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            has_st4_port: true,
+            ..Default::default()
+        }]));
+        let camera = Camera::with_backend(0, backend).init().unwrap();
+        assert!(camera.can_pulse_guide().unwrap());
+
+        let north = camera.start_pulse_guide(GuideDirection::North, Duration::from_millis(200)).unwrap();
+        assert!(!north.is_finished());
+        assert!(matches!(
+            camera.pulse_guide(GuideDirection::South, Duration::from_millis(10)),
+            Err(SVBError::PulseGuideInProgress(GuideDirection::North))
+        ));
+        // the other axis is free
+        let start = Instant::now();
+        camera.pulse_guide(GuideDirection::East, Duration::from_millis(50)).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        north.wait().unwrap();
+        camera.pulse_guide(GuideDirection::South, Duration::from_millis(10)).unwrap();
+
+        assert!(matches!(
+            camera.pulse_guide(GuideDirection::West, Duration::ZERO),
+            Err(SVBError::InvalidValue { kind: "pulse duration", .. })
+        ));
+    }
+}
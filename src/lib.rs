@@ -1,12 +1,25 @@
 #[macro_use]
 extern crate log;
 extern crate env_logger;
+#[cfg(feature = "tokio")]
+pub mod async_camera;
+pub mod backend;
 pub mod camera;
 pub mod debayer;
+pub mod fits;
+pub mod frame;
+pub mod guide;
 pub mod libsvb;
+pub mod optics;
+pub mod pool;
+#[cfg(feature = "dynamic")]
+pub mod sdk;
+pub mod simulator;
+pub mod snap;
+pub mod stream;
 pub mod utils;
+pub mod xisf;
 //pub mod capture_video;
-include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
 pub type BufType = Vec<u8>;
 pub type BufSize = i64;
@@ -2,16 +2,36 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 //#![allow(improper_ctypes)]
+// pre-generated from libsvb/include/SVBCameraSDK.h, regenerated with the `bindgen` feature
+#[cfg(not(feature = "bindgen"))]
+include!("bindings.rs");
+#[cfg(feature = "bindgen")]
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
-use log::{error, info};
+use std::ffi::CStr;
 use std::fmt;
+use std::os::raw::c_char;
+use std::time::Duration;
 use thiserror::Error;
 
+/// Call an SDK function, `Err` when the SDK library or the symbol is unavailable.
+#[cfg(feature = "dynamic")]
+macro_rules! sdk_call {
+    ($name:ident($($arg:expr),*)) => {
+        crate::sdk::library().and_then(|sdk| match sdk.$name {
+            Some(function) => Ok(unsafe { function($($arg),*) }),
+            None => Err(SVBError::MissingSymbol(stringify!($name))),
+        })
+    };
+}
+#[cfg(not(feature = "dynamic"))]
+macro_rules! sdk_call {
+    ($name:ident($($arg:expr),*)) => {
+        Ok::<_, SVBError>(unsafe { $name($($arg),*) })
+    };
+}
+
 #[derive(Error, Debug)]
 pub enum SVBError {
-    #[error("Success")]
-    Success,
-
     #[error("Invalid index: no camera connected or index value out of boundary")]
     InvalidIndex,
 
@@ -69,16 +89,99 @@ pub enum SVBError {
     #[error("Unknown sensor type: unknown sensor type")]
     UnknownSensorType,
 
+    #[error("Invalid {kind}: {value}")]
+    InvalidValue { kind: &'static str, value: i64 },
 
     #[error("Overflow resolution: overflow resolution")]
     OverFlowResolution,
-    
-    #[error("Unknown error")]
-    UnknownError,
+
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[error("Camera not found: no connected camera matches")]
+    CameraNotFound,
+
+    #[error("Ambiguous camera: {0} connected cameras match")]
+    AmbiguousCamera(usize),
+
+    #[error("Pulse guide in progress: a {0} pulse is still running on the same axis")]
+    PulseGuideInProgress(GuideDirection),
+
+    #[error("SVBCameraSDK could not be loaded from {path}: {reason}")]
+    LibraryNotLoaded { path: String, reason: String },
+
+    #[error("SVBCameraSDK does not export {0}")]
+    MissingSymbol(&'static str),
+
+    #[error("Unknown error code {0}")]
+    Unknown(i32),
+
+    /// `source` returned by the SDK function `call`.
+    #[error("{call}{} failed: {source}", .camera_id.map(|id| format!(" on camera {}", id)).unwrap_or_default())]
+    Call {
+        call: &'static str,
+        camera_id: Option<i32>,
+        source: Box<SVBError>,
+    },
+}
+
+impl SVBError {
+    /// The error without the SDK call context.
+    pub fn root(&self) -> &SVBError {
+        match self {
+            SVBError::Call { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    /// Name of the SDK function which failed, if known.
+    pub fn call(&self) -> Option<&'static str> {
+        match self {
+            SVBError::Call { call, .. } => Some(call),
+            _ => None,
+        }
+    }
+
+    /// Camera the failed SDK call was made on, if any.
+    pub fn camera_id(&self) -> Option<i32> {
+        match self {
+            SVBError::Call { camera_id, .. } => *camera_id,
+            _ => None,
+        }
+    }
+
+    /// The `SVB_ERROR_CODE` returned by the SDK, if the error came from one.
+    pub fn code(&self) -> Option<i32> {
+        let code = match self.root() {
+            SVBError::InvalidIndex => 1,
+            SVBError::InvalidId => 2,
+            SVBError::InvalidControlType => 3,
+            SVBError::CameraClosed => 4,
+            SVBError::CameraRemoved => 5,
+            SVBError::InvalidPath => 6,
+            SVBError::InvalidFileFormat => 7,
+            SVBError::InvalidSize => 8,
+            SVBError::InvalidImgType => 9,
+            SVBError::OutOfBoundary => 10,
+            SVBError::Timeout => 11,
+            SVBError::InvalidSequence => 12,
+            SVBError::BufferTooSmall => 13,
+            SVBError::VideoModeActive => 14,
+            SVBError::ExposureInProgress => 15,
+            SVBError::GeneralError => 16,
+            SVBError::InvalidMode => 17,
+            SVBError::InvalidDirection => 18,
+            SVBError::UnknownSensorType => 19,
+            SVBError::Unknown(code) => *code,
+            _ => return None,
+        };
+        Some(code)
+    }
 }
+
+/// Error of a non-zero `SVB_ERROR_CODE`.
 pub fn convert_err_code(code: i32) -> SVBError {
     match code {
-        0 => SVBError::Success,
         1 => SVBError::InvalidIndex,
         2 => SVBError::InvalidId,
         3 => SVBError::InvalidControlType,
@@ -97,18 +200,277 @@ pub fn convert_err_code(code: i32) -> SVBError {
         16 => SVBError::GeneralError,
         17 => SVBError::InvalidMode,
         18 => SVBError::InvalidDirection,
-        _ => SVBError::UnknownSensorType, // 不明なエラーコードの場合
+        19 => SVBError::UnknownSensorType,
+        code => SVBError::Unknown(code), // 不明なエラーコードの場合
+    }
+}
+
+/// Result of the SDK function `call` returning an error code.
+fn check(call: &'static str, camera_id: Option<i32>, ret: Result<i32, SVBError>) -> Result<(), SVBError> {
+    let source = match ret {
+        Ok(0) => return Ok(()),
+        Ok(code) => convert_err_code(code),
+        Err(e) => e,
+    };
+    Err(SVBError::Call {
+        call,
+        camera_id,
+        source: Box::new(source),
+    })
+}
+
+/// Define a typed counterpart of an SDK enum, convertible from and to its raw value.
+macro_rules! svb_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident: $kind:literal {
+            $($(#[$vmeta:meta])* $variant:ident = $value:expr => $label:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[non_exhaustive]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant = $value as isize,)*
+        }
+
+        impl $name {
+            /// Every value known to this crate, in SDK order.
+            pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+        }
+
+        impl TryFrom<u32> for $name {
+            type Error = SVBError;
+            fn try_from(value: u32) -> Result<Self, SVBError> {
+                match value {
+                    $(v if v == $value as u32 => Ok($name::$variant),)*
+                    value => Err(SVBError::InvalidValue { kind: $kind, value: value as i64 }),
+                }
+            }
+        }
+
+        impl TryFrom<i32> for $name {
+            type Error = SVBError;
+            fn try_from(value: i32) -> Result<Self, SVBError> {
+                u32::try_from(value)
+                    .map_err(|_| SVBError::InvalidValue { kind: $kind, value: value as i64 })
+                    .and_then($name::try_from)
+            }
+        }
+
+        impl From<$name> for u32 {
+            fn from(value: $name) -> u32 {
+                value as u32
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(value: $name) -> i32 {
+                value as i32
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let label = match self {
+                    $($name::$variant => $label,)*
+                };
+                write!(f, "{}", label)
+            }
+        }
+    };
+}
+
+svb_enum! {
+    /// Camera control, see `SVB_CONTROL_TYPE`.
+    pub enum ControlType: "control type" {
+        Gain = SVB_CONTROL_TYPE_SVB_GAIN => "Gain",
+        /// Exposure time in microseconds.
+        Exposure = SVB_CONTROL_TYPE_SVB_EXPOSURE => "Exposure",
+        Gamma = SVB_CONTROL_TYPE_SVB_GAMMA => "Gamma",
+        GammaContrast = SVB_CONTROL_TYPE_SVB_GAMMA_CONTRAST => "GammaContrast",
+        WbR = SVB_CONTROL_TYPE_SVB_WB_R => "WB_R",
+        WbG = SVB_CONTROL_TYPE_SVB_WB_G => "WB_G",
+        WbB = SVB_CONTROL_TYPE_SVB_WB_B => "WB_B",
+        /// Takes a `FlipStatus` value.
+        Flip = SVB_CONTROL_TYPE_SVB_FLIP => "Flip",
+        /// 0: low speed, 1: medium speed, 2: high speed.
+        FrameSpeedMode = SVB_CONTROL_TYPE_SVB_FRAME_SPEED_MODE => "FrameSpeedMode",
+        Contrast = SVB_CONTROL_TYPE_SVB_CONTRAST => "Contrast",
+        Sharpness = SVB_CONTROL_TYPE_SVB_SHARPNESS => "Sharpness",
+        Saturation = SVB_CONTROL_TYPE_SVB_SATURATION => "Saturation",
+        AutoTargetBrightness = SVB_CONTROL_TYPE_SVB_AUTO_TARGET_BRIGHTNESS => "AutoTargetBrightness",
+        /// Black level offset.
+        BlackLevel = SVB_CONTROL_TYPE_SVB_BLACK_LEVEL => "BlackLevel",
+        /// 0: disable, 1: enable.
+        CoolerEnable = SVB_CONTROL_TYPE_SVB_COOLER_ENABLE => "CoolerEnable",
+        /// Unit is 0.1 C.
+        TargetTemperature = SVB_CONTROL_TYPE_SVB_TARGET_TEMPERATURE => "TargetTemperature",
+        /// Unit is 0.1 C.
+        CurrentTemperature = SVB_CONTROL_TYPE_SVB_CURRENT_TEMPERATURE => "CurrentTemperature",
+        /// Range 0-100.
+        CoolerPower = SVB_CONTROL_TYPE_SVB_COOLER_POWER => "CoolerPower",
+        BadPixelCorrection = SVB_CONTROL_TYPE_SVB_BAD_PIXEL_CORRECTION_ENABLE => "BadPixelCorrection",
+    }
+}
+
+svb_enum! {
+    /// Output image format, see `SVB_IMG_TYPE`.
+    pub enum ImageType: "image type" {
+        Raw8 = SVB_IMG_TYPE_SVB_IMG_RAW8 => "RAW8",
+        Raw10 = SVB_IMG_TYPE_SVB_IMG_RAW10 => "RAW10",
+        Raw12 = SVB_IMG_TYPE_SVB_IMG_RAW12 => "RAW12",
+        Raw14 = SVB_IMG_TYPE_SVB_IMG_RAW14 => "RAW14",
+        Raw16 = SVB_IMG_TYPE_SVB_IMG_RAW16 => "RAW16",
+        Y8 = SVB_IMG_TYPE_SVB_IMG_Y8 => "Y8",
+        Y10 = SVB_IMG_TYPE_SVB_IMG_Y10 => "Y10",
+        Y12 = SVB_IMG_TYPE_SVB_IMG_Y12 => "Y12",
+        Y14 = SVB_IMG_TYPE_SVB_IMG_Y14 => "Y14",
+        Y16 = SVB_IMG_TYPE_SVB_IMG_Y16 => "Y16",
+        Rgb24 = SVB_IMG_TYPE_SVB_IMG_RGB24 => "RGB24",
+        Rgb32 = SVB_IMG_TYPE_SVB_IMG_RGB32 => "RGB32",
+    }
+}
+
+impl ImageType {
+    /// Size of one pixel in the buffer returned by the SDK.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            ImageType::Raw8 | ImageType::Y8 => 1,
+            ImageType::Rgb24 => 3,
+            ImageType::Rgb32 => 4,
+            _ => 2,
+        }
+    }
+
+    /// Whether the frame is the undebayered sensor data.
+    pub fn is_raw(&self) -> bool {
+        matches!(
+            self,
+            ImageType::Raw8 | ImageType::Raw10 | ImageType::Raw12 | ImageType::Raw14 | ImageType::Raw16
+        )
+    }
+}
+
+svb_enum! {
+    /// Color filter array layout, see `SVB_BAYER_PATTERN`.
+    pub enum BayerPattern: "bayer pattern" {
+        RG = SVB_BAYER_PATTERN_SVB_BAYER_RG => "RGGB",
+        BG = SVB_BAYER_PATTERN_SVB_BAYER_BG => "BGGR",
+        GR = SVB_BAYER_PATTERN_SVB_BAYER_GR => "GRBG",
+        GB = SVB_BAYER_PATTERN_SVB_BAYER_GB => "GBRG",
+    }
+}
+
+impl BayerPattern {
+    /// Pattern seen from a pixel `dx` columns and `dy` rows away from the top left one.
+    pub fn shifted(self, dx: i32, dy: i32) -> BayerPattern {
+        let (odd_x, odd_y) = (dx.rem_euclid(2) == 1, dy.rem_euclid(2) == 1);
+        // as (pattern with the columns swapped, pattern with the rows swapped)
+        let (swap_x, swap_y) = match self {
+            BayerPattern::RG => (BayerPattern::GR, BayerPattern::GB),
+            BayerPattern::BG => (BayerPattern::GB, BayerPattern::GR),
+            BayerPattern::GR => (BayerPattern::RG, BayerPattern::BG),
+            BayerPattern::GB => (BayerPattern::BG, BayerPattern::RG),
+        };
+        match (odd_x, odd_y) {
+            (false, false) => self,
+            (true, false) => swap_x,
+            (false, true) => swap_y,
+            (true, true) => swap_x.shifted(0, 1),
+        }
+    }
+}
+
+svb_enum! {
+    /// Value of the `ControlType::Flip` control, see `SVB_FLIP_STATUS`.
+    pub enum FlipStatus: "flip status" {
+        None = SVB_FLIP_STATUS_SVB_FLIP_NONE => "None",
+        Horizontal = SVB_FLIP_STATUS_SVB_FLIP_HORIZ => "Horizontal",
+        Vertical = SVB_FLIP_STATUS_SVB_FLIP_VERT => "Vertical",
+        Both = SVB_FLIP_STATUS_SVB_FLIP_BOTH => "Both",
+    }
+}
+
+svb_enum! {
+    /// Normal video mode or one of the trigger modes, see `SVB_CAMERA_MODE`.
+    pub enum CameraMode: "camera mode" {
+        Normal = SVB_CAMERA_MODE_SVB_MODE_NORMAL => "Normal",
+        TriggerSoft = SVB_CAMERA_MODE_SVB_MODE_TRIG_SOFT => "TriggerSoft",
+        TriggerRiseEdge = SVB_CAMERA_MODE_SVB_MODE_TRIG_RISE_EDGE => "TriggerRiseEdge",
+        TriggerFallEdge = SVB_CAMERA_MODE_SVB_MODE_TRIG_FALL_EDGE => "TriggerFallEdge",
+        TriggerDoubleEdge = SVB_CAMERA_MODE_SVB_MODE_TRIG_DOUBLE_EDGE => "TriggerDoubleEdge",
+        TriggerHighLevel = SVB_CAMERA_MODE_SVB_MODE_TRIG_HIGH_LEVEL => "TriggerHighLevel",
+        TriggerLowLevel = SVB_CAMERA_MODE_SVB_MODE_TRIG_LOW_LEVEL => "TriggerLowLevel",
+    }
+}
+
+svb_enum! {
+    /// State of a single exposure, see `SVB_EXPOSURE_STATUS`.
+    pub enum ExposureStatus: "exposure status" {
+        Idle = SVB_EXPOSURE_STATUS_SVB_EXP_IDLE => "Idle",
+        Working = SVB_EXPOSURE_STATUS_SVB_EXP_WORKING => "Working",
+        Success = SVB_EXPOSURE_STATUS_SVB_EXP_SUCCESS => "Success",
+        Failed = SVB_EXPOSURE_STATUS_SVB_EXP_FAILED => "Failed",
+    }
+}
+
+svb_enum! {
+    /// ST-4 guide direction, see `SVB_GUIDE_DIRECTION`.
+    pub enum GuideDirection: "guide direction" {
+        North = SVB_GUIDE_DIRECTION_SVB_GUIDE_NORTH => "North",
+        South = SVB_GUIDE_DIRECTION_SVB_GUIDE_SOUTH => "South",
+        East = SVB_GUIDE_DIRECTION_SVB_GUIDE_EAST => "East",
+        West = SVB_GUIDE_DIRECTION_SVB_GUIDE_WEST => "West",
+    }
+}
+
+svb_enum! {
+    /// Output pin of the trigger port, see `SVB_TRIG_OUTPUT_PIN`.
+    pub enum TriggerOutputPin: "trigger output pin" {
+        A = SVB_TRIG_OUTPUT_SVB_TRIG_OUTPUT_PINA => "A",
+        B = SVB_TRIG_OUTPUT_SVB_TRIG_OUTPUT_PINB => "B",
+    }
+}
+
+/// Signal of a trigger output pin, see `SVBSetTriggerOutputIOConf`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TriggerOutput {
+    /// Whether the pin outputs a high level while active, otherwise a low level.
+    pub active_high: bool,
+    /// Time between the camera receiving a trigger and the pin becoming active.
+    pub delay: Duration,
+    /// How long the pin stays active; zero disables the pin.
+    pub duration: Duration,
+}
+impl TriggerOutput {
+    /// Longest delay and duration the SDK accepts.
+    pub const MAX_TIME: Duration = Duration::from_secs(2000);
+
+    pub fn is_enabled(&self) -> bool {
+        !self.duration.is_zero()
+    }
+
+    /// Checks delay and duration against `MAX_TIME`.
+    pub fn validate(&self) -> Result<(), SVBError> {
+        for (kind, time) in [("trigger output delay", self.delay), ("trigger output duration", self.duration)] {
+            if time > Self::MAX_TIME {
+                error!("{} {:?} is over the maximum of {:?}", kind, time, Self::MAX_TIME);
+                return Err(SVBError::InvalidValue { kind, value: time.as_micros() as i64 });
+            }
+        }
+        Ok(())
     }
 }
 
 pub type SVBControlValue = i64;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct ControlTypeState {
     pub value: SVBControlValue,
     pub is_auto: i32,
 }
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub struct ROIFormat {
     pub startx: i32,
     pub starty: i32,
@@ -125,6 +487,84 @@ impl ROIFormat {
             bin : 0
         }
     }
+    /// Width of a ROI must be a multiple of this.
+    pub const WIDTH_ALIGN: i32 = 8;
+    /// Height of a ROI must be a multiple of this.
+    pub const HEIGHT_ALIGN: i32 = 2;
+
+    /// Checks the ROI against the sensor of `prop`.
+    /// The start position and size are in binned pixels.
+    pub fn validate(&self, prop: &SVB_CAMERA_PROPERTY) -> Result<(), SVBError> {
+        let bins = prop.supported_bins();
+        if !bins.contains(&self.bin) {
+            error!("bin {} is not supported, supported bins : {:?}", self.bin, bins);
+            return Err(SVBError::InvalidValue { kind: "bin", value: self.bin as i64 });
+        }
+        let (max_width, max_height) = prop.binned_size(self.bin);
+        if self.width <= 0 || self.height <= 0 || max_width < self.width || max_height < self.height {
+            error!(
+                "ROI size {}x{} is over max size. when binning is {}, max height : {}, max width : {}",
+                self.width, self.height, self.bin, max_height, max_width
+            );
+            return Err(SVBError::OverFlowResolution);
+        }
+        if self.width % Self::WIDTH_ALIGN != 0 || self.height % Self::HEIGHT_ALIGN != 0 {
+            error!(
+                "ROI size {}x{} is not aligned, width must be a multiple of {} and height a multiple of {}",
+                self.width, self.height, Self::WIDTH_ALIGN, Self::HEIGHT_ALIGN
+            );
+            return Err(SVBError::InvalidSize);
+        }
+        if self.startx < 0
+            || self.starty < 0
+            || max_width < self.startx + self.width
+            || max_height < self.starty + self.height
+        {
+            error!(
+                "ROI start ({}, {}) is out of boundary. when binning is {}, max startx : {}, max starty : {}",
+                self.startx,
+                self.starty,
+                self.bin,
+                max_width - self.width,
+                max_height - self.height
+            );
+            return Err(SVBError::OutOfBoundary);
+        }
+        Ok(())
+    }
+
+    /// The nearest ROI which passes `validate`.
+    ///
+    /// The bin falls back to the largest supported bin below it (or the smallest
+    /// supported one), the size is rounded down to the alignment and clamped to
+    /// the binned sensor, and the start is moved so the ROI fits on the sensor.
+    pub fn snapped(&self, prop: &SVB_CAMERA_PROPERTY) -> ROIFormat {
+        let bins = prop.supported_bins();
+        let bin = if bins.contains(&self.bin) {
+            self.bin
+        } else {
+            bins.iter()
+                .copied()
+                .filter(|&b| b < self.bin)
+                .max()
+                .or_else(|| bins.iter().copied().min())
+                .unwrap_or(1)
+        };
+        let (max_width, max_height) = prop.binned_size(bin);
+        let snap = |value: i32, max: i32, align: i32| {
+            let max = max - max % align;
+            (value - value % align).clamp(align.min(max), max)
+        };
+        let width = snap(self.width, max_width, Self::WIDTH_ALIGN);
+        let height = snap(self.height, max_height, Self::HEIGHT_ALIGN);
+        ROIFormat {
+            startx: self.startx.clamp(0, (max_width - width).max(0)),
+            starty: self.starty.clamp(0, (max_height - height).max(0)),
+            width,
+            height,
+            bin,
+        }
+    }
 }
 impl fmt::Display for ROIFormat {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -135,12 +575,34 @@ impl fmt::Display for ROIFormat {
         )
     }
 }
+/// Owned copy of a NUL terminated string the SDK wrote into `chars`.
+pub fn c_str_to_string(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars.iter().map(|&c| c as u8).take_while(|&b| b != 0).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+impl Default for SVB_SN {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl SVB_SN {
     pub fn new() -> Self {
         Self { id: [0; 64] }
     }
 }
+impl fmt::Display for SVB_SN {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let len = self.id.iter().position(|&b| b == 0).unwrap_or(self.id.len());
+        write!(f, "{}", String::from_utf8_lossy(&self.id[..len]))
+    }
+}
 
+impl Default for SVB_CAMERA_INFO {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl SVB_CAMERA_INFO {
     pub fn new() -> SVB_CAMERA_INFO {
         Self {
@@ -168,6 +630,11 @@ impl fmt::Display for SVB_CAMERA_INFO {
         )
     }
 }
+impl Default for SVB_CAMERA_PROPERTY {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl SVB_CAMERA_PROPERTY {
     pub fn new() -> SVB_CAMERA_PROPERTY {
         Self {
@@ -181,6 +648,16 @@ impl SVB_CAMERA_PROPERTY {
             IsTriggerCam: 0,
         }
     }
+    /// `SupportedBins` up to its terminating 0.
+    pub fn supported_bins(&self) -> Vec<i32> {
+        self.SupportedBins.iter().copied().take_while(|&b| b != 0).collect()
+    }
+
+    /// Sensor width and height in pixels when binning by `bin`.
+    pub fn binned_size(&self, bin: i32) -> (i32, i32) {
+        let bin = bin.max(1);
+        (self.MaxWidth as i32 / bin, self.MaxHeight as i32 / bin)
+    }
 }
 impl fmt::Display for SVB_CAMERA_PROPERTY {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -199,6 +676,35 @@ impl fmt::Display for SVB_CAMERA_PROPERTY {
     }
 }
 
+impl Default for SVB_CAMERA_PROPERTY_EX {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SVB_CAMERA_PROPERTY_EX {
+    pub fn new() -> Self {
+        Self {
+            bSupportPulseGuide: 0,
+            bSupportControlTemp: 0,
+            Unused: [0; 64],
+        }
+    }
+}
+impl fmt::Display for SVB_CAMERA_PROPERTY_EX {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "\nSupportPulseGuide: {}\nSupportControlTemp: {}\n",
+            self.bSupportPulseGuide, self.bSupportControlTemp
+        )
+    }
+}
+
+impl Default for SVB_CONTROL_CAPS {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl SVB_CONTROL_CAPS {
     pub fn new() -> Self {
         Self {
@@ -231,8 +737,33 @@ impl fmt::Display for SVB_CONTROL_CAPS {
     }
 }
 
-pub fn _get_num_of_connected_cameras() -> i32 {
-    unsafe { SVBGetNumOfConnectedCameras() }
+impl Default for SVB_SUPPORTED_MODE {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SVB_SUPPORTED_MODE {
+    pub fn new() -> Self {
+        Self {
+            SupportedCameraMode: [SVB_CAMERA_MODE_SVB_MODE_END; 16],
+        }
+    }
+    /// `SupportedCameraMode` up to its terminating `SVB_MODE_END`.
+    pub fn modes(&self) -> Vec<SVB_CAMERA_MODE> {
+        self.SupportedCameraMode
+            .iter()
+            .copied()
+            .take_while(|&mode| mode != SVB_CAMERA_MODE_SVB_MODE_END)
+            .collect()
+    }
+}
+
+pub fn _get_num_of_connected_cameras() -> Result<i32, SVBError> {
+    sdk_call!(SVBGetNumOfConnectedCameras()).map_err(|e| SVBError::Call {
+        call: "SVBGetNumOfConnectedCameras",
+        camera_id: None,
+        source: Box::new(e),
+    })
 }
 
 /***************************************************************************
@@ -249,8 +780,8 @@ SVB_ERROR_INVALID_ID  : no camera of this ID is connected or ID value is out of
 SVB_ERROR_CAMERA_REMOVED: failed to find the camera, maybe camera has been removed
 
 ***************************************************************************/
-pub fn _open_camera(camera_id: i32) -> SVBError {
-    convert_err_code(unsafe { SVBOpenCamera(camera_id) })
+pub fn _open_camera(camera_id: i32) -> Result<(), SVBError> {
+    check("SVBOpenCamera", Some(camera_id), sdk_call!(SVBOpenCamera(camera_id)))
 }
 
 /***************************************************************************
@@ -267,8 +798,8 @@ SVB_ERROR_INVALID_ID  : no camera of this ID is connected or ID value is out of
 SVB_ERROR_CAMERA_REMOVED: failed to find the camera, maybe camera has been removed
 
 *************************************************************/
-pub fn _close_camera(camera_id: i32) -> SVBError {
-    convert_err_code(unsafe { SVBCloseCamera(camera_id) })
+pub fn _close_camera(camera_id: i32) -> Result<(), SVBError> {
+    check("SVBCloseCamera", Some(camera_id), sdk_call!(SVBCloseCamera(camera_id)))
 }
 
 /***************************************************************************
@@ -287,8 +818,14 @@ SVB_ERROR_CAMERA_CLOSED : camera didn't open
 SVB_ERROR_INVALID_ID  :no camera of this ID is connected or ID value is out of boundary
 ***************************************************************************/
 
-pub fn _get_num_of_controls(camera_id: i32, num_ctls: &mut i32) -> i32 {
-    unsafe { SVBGetNumOfControls(camera_id, num_ctls) }
+pub fn _get_num_of_controls(camera_id: i32) -> Result<i32, SVBError> {
+    let mut num_ctls = 0;
+    check(
+        "SVBGetNumOfControls",
+        Some(camera_id),
+        sdk_call!(SVBGetNumOfControls(camera_id, &mut num_ctls)),
+    )?;
+    Ok(num_ctls)
 }
 
 /***************************************************************************
@@ -309,8 +846,14 @@ SVB_SUCCESS : Operation is successful
 SVB_ERROR_CAMERA_CLOSED : camera didn't open
 SVB_ERROR_INVALID_ID  :no camera of this ID is connected or ID value is out of boundary
 ***************************************************************************/
-pub fn _get_ctl_caps(camera_id: i32, ctl_idx: i32, ctl_caps: &mut SVB_CONTROL_CAPS) -> i32 {
-    unsafe { SVBGetControlCaps(camera_id, ctl_idx, ctl_caps) }
+pub fn _get_ctl_caps(camera_id: i32, ctl_idx: i32) -> Result<SVB_CONTROL_CAPS, SVBError> {
+    let mut ctl_caps = SVB_CONTROL_CAPS::new();
+    check(
+        "SVBGetControlCaps",
+        Some(camera_id),
+        sdk_call!(SVBGetControlCaps(camera_id, ctl_idx, &mut ctl_caps)),
+    )?;
+    Ok(ctl_caps)
 }
 
 /***************************************************************************
@@ -330,10 +873,22 @@ SVB_ERROR_INVALID_INDEX  :no camera connected or index value out of boundary
 ************************************/
 pub fn _get_camera_prop(camera_id: i32) -> Result<SVB_CAMERA_PROPERTY, SVBError> {
     let mut camera_prop = SVB_CAMERA_PROPERTY::new();
-    match unsafe { SVBGetCameraProperty(camera_id, &mut camera_prop) } {
-        0 => Ok(camera_prop),
-        code => Err(convert_err_code(code)),
-    }
+    check(
+        "SVBGetCameraProperty",
+        Some(camera_id),
+        sdk_call!(SVBGetCameraProperty(camera_id, &mut camera_prop)),
+    )?;
+    Ok(camera_prop)
+}
+
+pub fn _get_camera_prop_ex(camera_id: i32) -> Result<SVB_CAMERA_PROPERTY_EX, SVBError> {
+    let mut camera_prop_ex = SVB_CAMERA_PROPERTY_EX::new();
+    check(
+        "SVBGetCameraPropertyEx",
+        Some(camera_id),
+        sdk_call!(SVBGetCameraPropertyEx(camera_id, &mut camera_prop_ex)),
+    )?;
+    Ok(camera_prop_ex)
 }
 
 /***************************************************************************
@@ -361,10 +916,13 @@ return:
 ************************************************/
 pub fn _get_camera_info(camera_idx: i32) -> Result<SVB_CAMERA_INFO, SVBError> {
     let mut camera_info = SVB_CAMERA_INFO::new();
-    match unsafe { SVBGetCameraInfo(&mut camera_info, camera_idx) } {
-        0 => Ok(camera_info),
-        e => Err(convert_err_code(e)),
-    }
+    // the SDK takes the camera index here, there is no camera id yet
+    check(
+        "SVBGetCameraInfo",
+        None,
+        sdk_call!(SVBGetCameraInfo(&mut camera_info, camera_idx)),
+    )?;
+    Ok(camera_info)
 }
 
 /***************************************************************************
@@ -383,15 +941,21 @@ SVB_ERROR_CAMERA_CLOSED : camera didn't open
 SVB_ERROR_INVALID_ID  :no camera of this ID is connected or ID value is out of boundary
 
 ********************************************************************/
-pub fn _get_roi_format(
-    camera_id: i32,
-    startx: &mut i32,
-    starty: &mut i32,
-    width: &mut i32,
-    height: &mut i32,
-    bin: &mut i32,
-) -> SVBError {
-    convert_err_code(unsafe { SVBGetROIFormat(camera_id, startx, starty, width, height, bin) })
+pub fn _get_roi_format(camera_id: i32) -> Result<ROIFormat, SVBError> {
+    let mut roi = ROIFormat::new();
+    check(
+        "SVBGetROIFormat",
+        Some(camera_id),
+        sdk_call!(SVBGetROIFormat(
+            camera_id,
+            &mut roi.startx,
+            &mut roi.starty,
+            &mut roi.width,
+            &mut roi.height,
+            &mut roi.bin
+        )),
+    )?;
+    Ok(roi)
 }
 
 /***************************************************************************
@@ -422,8 +986,12 @@ pub fn _set_roi_format(
     width: i32,
     height: i32,
     bin: i32,
-) -> SVBError {
-    convert_err_code(unsafe { SVBSetROIFormat(camera_id, startx, starty, width, height, bin) })
+) -> Result<(), SVBError> {
+    check(
+        "SVBSetROIFormat",
+        Some(camera_id),
+        sdk_call!(SVBSetROIFormat(camera_id, startx, starty, width, height, bin)),
+    )
 }
 
 /***************************************************************************
@@ -442,8 +1010,8 @@ SVB_ERROR_INVALID_ID  :no camera of this ID is connected or ID value is out of b
 SVB_ERROR_EXPOSURE_IN_PROGRESS: snap mode is working, you need to stop snap first
 ***********************************************************************/
 
-pub fn _start_video_capture(camera_id: i32) -> SVBError {
-    convert_err_code(unsafe { SVBStartVideoCapture(camera_id) })
+pub fn _start_video_capture(camera_id: i32) -> Result<(), SVBError> {
+    check("SVBStartVideoCapture", Some(camera_id), sdk_call!(SVBStartVideoCapture(camera_id)))
 }
 
 /***************************************************************************
@@ -473,12 +1041,17 @@ SVB_ERROR_INVALID_ID  :no camera of this ID is connected or ID value is out of b
 SVB_ERROR_TIMEOUT: no image get and timeout
 *********************************************************/
 
-pub fn _get_video_data(camera_id: i32, pbuf: *mut u8, buf_size: i64, wait_ms: i32) -> SVBError {
-    convert_err_code(unsafe { SVBGetVideoData(camera_id, pbuf, buf_size, wait_ms) })
+pub fn _get_video_data(camera_id: i32, buf: &mut [u8], wait_ms: i32) -> Result<(), SVBError> {
+    let buf_size = buf.len() as i64;
+    check(
+        "SVBGetVideoData",
+        Some(camera_id),
+        sdk_call!(SVBGetVideoData(camera_id, buf.as_mut_ptr(), buf_size, wait_ms)),
+    )
 }
 
-pub fn _stop_video_capture(camera_id: i32) -> SVBError {
-    convert_err_code(unsafe { SVBStopVideoCapture(camera_id) })
+pub fn _stop_video_capture(camera_id: i32) -> Result<(), SVBError> {
+    check("SVBStopVideoCapture", Some(camera_id), sdk_call!(SVBStopVideoCapture(camera_id)))
 }
 
 /***************************************************************************
@@ -496,8 +1069,12 @@ SVB_ERROR_INVALID_ID  :no camera of this ID is connected or ID value is out of b
 SVB_ERROR_INVALID_IMGTYPE, //invalid image type
 SVB_ERROR_GENERAL_ERROR,//general error, eg: value is out of valid range; operate to camera hareware failed
 ********************************************************/
-pub fn _set_img_type(camera_id: i32, img_type: SVB_IMG_TYPE) -> SVBError {
-    convert_err_code(unsafe { SVBSetOutputImageType(camera_id, img_type) })
+pub fn _set_img_type(camera_id: i32, img_type: SVB_IMG_TYPE) -> Result<(), SVBError> {
+    check(
+        "SVBSetOutputImageType",
+        Some(camera_id),
+        sdk_call!(SVBSetOutputImageType(camera_id, img_type)),
+    )
 }
 
 /***************************************************************************
@@ -514,8 +1091,14 @@ SVB_ERROR_CAMERA_CLOSED : camera didn't open
 SVB_ERROR_INVALID_ID  :no camera of this ID is connected or ID value is out of boundary
 SVB_ERROR_GENERAL_ERROR,//general error, eg: value is out of valid range; operate to camera hareware failed
 ***************************************************************/
-pub fn _get_img_type(camera_id: i32, img_type: &mut SVB_IMG_TYPE) -> SVBError {
-    convert_err_code(unsafe { SVBGetOutputImageType(camera_id, img_type) })
+pub fn _get_img_type(camera_id: i32) -> Result<SVB_IMG_TYPE, SVBError> {
+    let mut img_type = 0;
+    check(
+        "SVBGetOutputImageType",
+        Some(camera_id),
+        sdk_call!(SVBGetOutputImageType(camera_id, &mut img_type)),
+    )?;
+    Ok(img_type)
 }
 
 /***************************************************************************
@@ -536,13 +1119,19 @@ SVB_ERROR_CAMERA_CLOSED : camera didn't open
 SVB_ERROR_INVALID_ID  :no camera of this ID is connected or ID value is out of boundary
 SVB_ERROR_INVALID_CONTROL_TYPE, //invalid Control type
 ***************************************************************/
-pub fn _get_ctl_value(
-    camera_id: i32,
-    ctl_type: SVB_CONTROL_TYPE,
-    value: &mut SVBControlValue,
-    is_auto: &mut i32,
-) -> SVBError {
-    convert_err_code(unsafe { SVBGetControlValue(camera_id, ctl_type as i32, value, is_auto) })
+pub fn _get_ctl_value(camera_id: i32, ctl_type: SVB_CONTROL_TYPE) -> Result<ControlTypeState, SVBError> {
+    let mut state = ControlTypeState { value: 0, is_auto: 0 };
+    check(
+        "SVBGetControlValue",
+        Some(camera_id),
+        sdk_call!(SVBGetControlValue(
+            camera_id,
+            ctl_type as i32,
+            &mut state.value,
+            &mut state.is_auto
+        )),
+    )?;
+    Ok(state)
 }
 
 /***************************************************************************
@@ -570,24 +1159,253 @@ pub fn _set_ctl_value(
     ctl_type: SVB_CONTROL_TYPE,
     value: SVBControlValue,
     is_auto: SVB_BOOL,
-) -> SVBError {
-    convert_err_code(unsafe {
-        SVBSetControlValue(camera_id, ctl_type as i32, value, is_auto as i32)
-    })
+) -> Result<(), SVBError> {
+    check(
+        "SVBSetControlValue",
+        Some(camera_id),
+        sdk_call!(SVBSetControlValue(camera_id, ctl_type as i32, value, is_auto as i32)),
+    )
 }
 
-pub fn _get_serial_number(camera_id: i32, sn: *mut SVB_SN) -> SVBError {
-    convert_err_code(unsafe { SVBGetSerialNumber(camera_id, sn) })
+pub fn _get_serial_number(camera_id: i32) -> Result<SVB_SN, SVBError> {
+    let mut sn = SVB_SN::new();
+    check("SVBGetSerialNumber", Some(camera_id), sdk_call!(SVBGetSerialNumber(camera_id, &mut sn)))?;
+    Ok(sn)
 }
 
-pub fn _get_droped_frame(camera_id: i32, num_droppd_frame: &mut i32) -> SVBError {
-    convert_err_code(unsafe { SVBGetDroppedFrames(camera_id, num_droppd_frame) })
+pub fn _get_droped_frame(camera_id: i32) -> Result<i32, SVBError> {
+    let mut num_droppd_frame = 0;
+    check(
+        "SVBGetDroppedFrames",
+        Some(camera_id),
+        sdk_call!(SVBGetDroppedFrames(camera_id, &mut num_droppd_frame)),
+    )?;
+    Ok(num_droppd_frame)
 }
 
 
 
 pub fn _adjust_white_balance(
     camera_id: i32,
-) -> SVBError {
-    convert_err_code(unsafe { SVBWhiteBalanceOnce(camera_id ) })
-}
\ No newline at end of file
+) -> Result<(), SVBError> {
+    check("SVBWhiteBalanceOnce", Some(camera_id), sdk_call!(SVBWhiteBalanceOnce(camera_id)))
+}
+
+pub fn _get_camera_support_mode(camera_id: i32) -> Result<SVB_SUPPORTED_MODE, SVBError> {
+    let mut modes = SVB_SUPPORTED_MODE::new();
+    check(
+        "SVBGetCameraSupportMode",
+        Some(camera_id),
+        sdk_call!(SVBGetCameraSupportMode(camera_id, &mut modes)),
+    )?;
+    Ok(modes)
+}
+
+pub fn _get_camera_mode(camera_id: i32) -> Result<SVB_CAMERA_MODE, SVBError> {
+    let mut mode = SVB_CAMERA_MODE_SVB_MODE_NORMAL;
+    check("SVBGetCameraMode", Some(camera_id), sdk_call!(SVBGetCameraMode(camera_id, &mut mode)))?;
+    Ok(mode)
+}
+
+/// Only needed when `SVB_CAMERA_PROPERTY::IsTriggerCam` is set; fails with
+/// `InvalidSequence` while capturing.
+pub fn _set_camera_mode(camera_id: i32, mode: SVB_CAMERA_MODE) -> Result<(), SVBError> {
+    check("SVBSetCameraMode", Some(camera_id), sdk_call!(SVBSetCameraMode(camera_id, mode)))
+}
+
+pub fn _send_soft_trigger(camera_id: i32) -> Result<(), SVBError> {
+    check("SVBSendSoftTrigger", Some(camera_id), sdk_call!(SVBSendSoftTrigger(camera_id)))
+}
+
+/// Only needed when `SVB_CAMERA_PROPERTY::IsTriggerCam` is set; delay and
+/// duration are passed in µs.
+pub fn _set_trigger_output_io_conf(
+    camera_id: i32,
+    pin: SVB_TRIG_OUTPUT_PIN,
+    output: TriggerOutput,
+) -> Result<(), SVBError> {
+    check(
+        "SVBSetTriggerOutputIOConf",
+        Some(camera_id),
+        sdk_call!(SVBSetTriggerOutputIOConf(
+            camera_id,
+            pin,
+            output.active_high as i32,
+            output.delay.as_micros() as _,
+            output.duration.as_micros() as _
+        )),
+    )
+}
+
+pub fn _get_trigger_output_io_conf(camera_id: i32, pin: SVB_TRIG_OUTPUT_PIN) -> Result<TriggerOutput, SVBError> {
+    let mut pin_high = 0;
+    let mut delay = 0;
+    let mut duration = 0;
+    check(
+        "SVBGetTriggerOutputIOConf",
+        Some(camera_id),
+        sdk_call!(SVBGetTriggerOutputIOConf(camera_id, pin, &mut pin_high, &mut delay, &mut duration)),
+    )?;
+    Ok(TriggerOutput {
+        active_high: pin_high != 0,
+        delay: Duration::from_micros(delay.max(0) as u64),
+        duration: Duration::from_micros(duration.max(0) as u64),
+    })
+}
+
+pub fn _can_pulse_guide(camera_id: i32) -> Result<bool, SVBError> {
+    let mut can_pulse_guide = 0;
+    check(
+        "SVBCanPulseGuide",
+        Some(camera_id),
+        sdk_call!(SVBCanPulseGuide(camera_id, &mut can_pulse_guide)),
+    )?;
+    Ok(can_pulse_guide != 0)
+}
+
+/// Send an ST-4 pulse of `duration` ms in `direction` to the mount.
+pub fn _pulse_guide(camera_id: i32, direction: SVB_GUIDE_DIRECTION, duration: i32) -> Result<(), SVBError> {
+    check(
+        "SVBPulseGuide",
+        Some(camera_id),
+        sdk_call!(SVBPulseGuide(camera_id, direction as i32, duration)),
+    )
+}
+
+pub fn _get_camera_firmware_version(camera_id: i32) -> Result<String, SVBError> {
+    // the SDK needs at least 64 bytes
+    let mut version = [0 as c_char; 64];
+    check(
+        "SVBGetCameraFirmwareVersion",
+        Some(camera_id),
+        sdk_call!(SVBGetCameraFirmwareVersion(camera_id, version.as_mut_ptr())),
+    )?;
+    Ok(c_str_to_string(&version))
+}
+
+/// Version of the SDK, like "1, 13, 0503".
+pub fn _get_sdk_version() -> Result<String, SVBError> {
+    let version = sdk_call!(SVBGetSDKVersion()).map_err(|e| SVBError::Call {
+        call: "SVBGetSDKVersion",
+        camera_id: None,
+        source: Box::new(e),
+    })?;
+    if version.is_null() {
+        return Ok(String::new());
+    }
+    // the SDK returns a static string
+    Ok(unsafe { CStr::from_ptr(version) }.to_string_lossy().into_owned())
+}
+
+/// Whether the camera firmware needs an upgrade, and the oldest firmware
+/// version which doesn't.
+pub fn _is_camera_need_to_upgrade(camera_id: i32) -> Result<(bool, String), SVBError> {
+    let mut need_upgrade = 0;
+    let mut min_version = [0 as c_char; 64];
+    check(
+        "SVBIsCameraNeedToUpgrade",
+        Some(camera_id),
+        sdk_call!(SVBIsCameraNeedToUpgrade(camera_id, &mut need_upgrade, min_version.as_mut_ptr())),
+    )?;
+    Ok((need_upgrade != 0, c_str_to_string(&min_version)))
+}
+
+/// Sensor pixel size in µm; `UnknownSensorType` if the SDK doesn't know the sensor.
+pub fn _get_sensor_pixel_size(camera_id: i32) -> Result<f32, SVBError> {
+    let mut pixel_size = 0.0;
+    check(
+        "SVBGetSensorPixelSize",
+        Some(camera_id),
+        sdk_call!(SVBGetSensorPixelSize(camera_id, &mut pixel_size)),
+    )?;
+    Ok(pixel_size)
+}
+
+/// Whether the camera saves its parameters, so they survive closing it.
+pub fn _set_auto_save_param(camera_id: i32, enable: bool) -> Result<(), SVBError> {
+    check(
+        "SVBSetAutoSaveParam",
+        Some(camera_id),
+        sdk_call!(SVBSetAutoSaveParam(camera_id, enable as i32)),
+    )
+}
+
+/// Reset the parameters of the camera to the factory defaults.
+pub fn _restore_default_param(camera_id: i32) -> Result<(), SVBError> {
+    check("SVBRestoreDefaultParam", Some(camera_id), sdk_call!(SVBRestoreDefaultParam(camera_id)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_enum_conversion() {
+        for &ctl_type in ControlType::ALL {
+            assert_eq!(ControlType::try_from(u32::from(ctl_type)).unwrap(), ctl_type);
+        }
+        for &img_type in ImageType::ALL {
+            assert_eq!(ImageType::try_from(i32::from(img_type)).unwrap(), img_type);
+        }
+        assert_eq!(ImageType::try_from(SVB_IMG_TYPE_SVB_IMG_RAW16).unwrap(), ImageType::Raw16);
+        assert_eq!(BayerPattern::try_from(2u32).unwrap(), BayerPattern::GR);
+        assert!(matches!(
+            BayerPattern::try_from(4u32),
+            Err(SVBError::InvalidValue { kind: "bayer pattern", value: 4 })
+        ));
+        assert!(ImageType::try_from(SVB_IMG_TYPE_SVB_IMG_END).is_err());
+        assert!(ControlType::try_from(100u32).is_err());
+
+        assert_eq!(BayerPattern::RG.shifted(0, 0), BayerPattern::RG);
+        assert_eq!(BayerPattern::RG.shifted(1, 0), BayerPattern::GR);
+        assert_eq!(BayerPattern::RG.shifted(0, 3), BayerPattern::GB);
+        assert_eq!(BayerPattern::RG.shifted(-1, 1), BayerPattern::BG);
+        assert_eq!(BayerPattern::GB.shifted(1, 1), BayerPattern::GR);
+    }
+
+    #[test]
+    fn test_error_context() {
+        let e = check("SVBGetVideoData", Some(3), Ok(11)).unwrap_err();
+        assert!(matches!(e.root(), SVBError::Timeout));
+        assert_eq!(e.call(), Some("SVBGetVideoData"));
+        assert_eq!(e.camera_id(), Some(3));
+        assert_eq!(e.code(), Some(11));
+        assert_eq!(e.to_string(), "SVBGetVideoData on camera 3 failed: Timeout");
+
+        let e = check("SVBGetCameraInfo", None, Ok(42)).unwrap_err();
+        assert!(matches!(e.root(), SVBError::Unknown(42)));
+        assert_eq!(e.code(), Some(42));
+        assert_eq!(e.to_string(), "SVBGetCameraInfo failed: Unknown error code 42");
+
+        assert!(matches!(convert_err_code(19), SVBError::UnknownSensorType));
+        assert!(check("SVBOpenCamera", Some(0), Ok(0)).is_ok());
+    }
+
+    #[test]
+    fn test_roi_validation() {
+        let mut prop = SVB_CAMERA_PROPERTY::new();
+        prop.MaxWidth = 640;
+        prop.MaxHeight = 482;
+        prop.SupportedBins[..2].copy_from_slice(&[1, 2]);
+        let roi = |startx, starty, width, height, bin| ROIFormat { startx, starty, width, height, bin };
+
+        assert!(roi(0, 0, 640, 482, 1).validate(&prop).is_ok());
+        assert!(roi(8, 1, 312, 240, 2).validate(&prop).is_ok());
+        // height is compared against MaxHeight, not MaxWidth
+        assert!(matches!(roi(0, 0, 320, 484, 1).validate(&prop), Err(SVBError::OverFlowResolution)));
+        assert!(matches!(roi(0, 0, 328, 240, 2).validate(&prop), Err(SVBError::OverFlowResolution)));
+        assert!(matches!(roi(0, 0, 160, 120, 3).validate(&prop), Err(SVBError::InvalidValue { kind: "bin", value: 3 })));
+        assert!(matches!(roi(0, 0, 164, 120, 1).validate(&prop), Err(SVBError::InvalidSize)));
+        assert!(matches!(roi(0, 0, 160, 121, 1).validate(&prop), Err(SVBError::InvalidSize)));
+        assert!(matches!(roi(488, 0, 160, 120, 1).validate(&prop), Err(SVBError::OutOfBoundary)));
+        assert!(matches!(roi(0, -2, 160, 120, 1).validate(&prop), Err(SVBError::OutOfBoundary)));
+
+        assert_eq!(roi(0, 0, 640, 482, 1).snapped(&prop), roi(0, 0, 640, 482, 1));
+        assert_eq!(roi(500, 7, 165, 121, 1).snapped(&prop), roi(480, 7, 160, 120, 1));
+        assert_eq!(roi(0, 0, 640, 482, 2).snapped(&prop), roi(0, 0, 320, 240, 2));
+        assert_eq!(roi(-4, 0, 3, 1, 3).snapped(&prop), roi(0, 0, 8, 2, 2));
+        for r in [roi(500, 7, 165, 121, 1), roi(10, 300, 1000, 1000, 4), roi(-4, 0, 3, 1, 0)] {
+            assert!(r.snapped(&prop).validate(&prop).is_ok(), "{:?}", r.snapped(&prop));
+        }
+    }
+}
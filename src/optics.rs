@@ -0,0 +1,150 @@
+use crate::libsvb::ROIFormat;
+
+/// Arc seconds per radian divided by 1000, for µm pixels and mm focal lengths.
+const ARCSEC_PER_UM_PER_MM: f64 = 206.264_806;
+
+/// Seeing assumed by `Optics::new`, FWHM in arc seconds.
+pub const DEFAULT_SEEING_ARCSEC: f64 = 2.0;
+
+/// Telescope and sensor geometry, see `Camera::optics`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Optics {
+    /// Effective focal length in mm, including reducers and barlows.
+    pub focal_length_mm: f64,
+    /// Unbinned pixel size in µm.
+    pub pixel_size_um: f64,
+    /// Expected FWHM of stars in arc seconds, which the sampling advice is based on.
+    pub seeing_arcsec: f64,
+}
+
+/// How well stars are sampled, by the rule of thumb of 2 to 3 pixels per FWHM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sampling {
+    /// Less than 2 pixels per FWHM, stars look blocky; use a smaller bin or a
+    /// longer focal length.
+    Undersampled,
+    Optimal,
+    /// More than 3 pixels per FWHM, which costs signal to noise for no detail;
+    /// bin or use a reducer.
+    Oversampled,
+}
+
+/// Sky geometry of a frame, computed by `Optics::geometry`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageGeometry {
+    pub focal_length_mm: f64,
+    /// Pixel size in µm including binning.
+    pub pixel_size_um: f64,
+    /// Arc seconds per pixel.
+    pub plate_scale: f64,
+    /// Field of view in arc minutes.
+    pub fov_width_arcmin: f64,
+    pub fov_height_arcmin: f64,
+    /// Pixels per FWHM of the assumed seeing.
+    pub pixels_per_fwhm: f64,
+    pub sampling: Sampling,
+}
+
+impl Optics {
+    pub fn new(focal_length_mm: f64, pixel_size_um: f64) -> Self {
+        Self {
+            focal_length_mm,
+            pixel_size_um,
+            seeing_arcsec: DEFAULT_SEEING_ARCSEC,
+        }
+    }
+
+    pub fn with_seeing(self, seeing_arcsec: f64) -> Self {
+        Self { seeing_arcsec, ..self }
+    }
+
+    /// Arc seconds per pixel when binning by `bin`.
+    pub fn plate_scale(&self, bin: i32) -> f64 {
+        ARCSEC_PER_UM_PER_MM * self.pixel_size_um * bin.max(1) as f64 / self.focal_length_mm
+    }
+
+    pub fn sampling(&self, bin: i32) -> Sampling {
+        let pixels_per_fwhm = self.seeing_arcsec / self.plate_scale(bin);
+        if pixels_per_fwhm < 2.0 {
+            Sampling::Undersampled
+        } else if pixels_per_fwhm > 3.0 {
+            Sampling::Oversampled
+        } else {
+            Sampling::Optimal
+        }
+    }
+
+    /// The largest of `bins` which doesn't undersample, or the smallest if
+    /// every bin does.
+    pub fn recommended_bin(&self, bins: &[i32]) -> Option<i32> {
+        bins.iter()
+            .copied()
+            .filter(|&bin| self.sampling(bin) != Sampling::Undersampled)
+            .max()
+            .or_else(|| bins.iter().copied().min())
+    }
+
+    /// Plate scale, field of view and sampling of frames captured with `roi`.
+    pub fn geometry(&self, roi: &ROIFormat) -> ImageGeometry {
+        let bin = roi.bin.max(1);
+        let plate_scale = self.plate_scale(bin);
+        ImageGeometry {
+            focal_length_mm: self.focal_length_mm,
+            pixel_size_um: self.pixel_size_um * bin as f64,
+            plate_scale,
+            fov_width_arcmin: plate_scale * roi.width as f64 / 60.0,
+            fov_height_arcmin: plate_scale * roi.height as f64 / 60.0,
+            pixels_per_fwhm: self.seeing_arcsec / plate_scale,
+            sampling: self.sampling(bin),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Optics, Sampling};
+    use crate::camera::Camera;
+    use crate::libsvb::{ControlType, ROIFormat};
+    use crate::simulator::{SimulatedBackend, SimulatedCameraConfig};
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_geometry() {
+        // 3.76 µm pixels at 1000 mm
+        let optics = Optics::new(1000.0, 3.76);
+        assert!((optics.plate_scale(1) - 0.7756).abs() < 1e-4);
+        let roi = ROIFormat {
+            startx: 0,
+            starty: 0,
+            width: 3000,
+            height: 2000,
+            bin: 2,
+        };
+        let geometry = optics.geometry(&roi);
+        assert!((geometry.plate_scale - 1.5511).abs() < 1e-4);
+        assert!((geometry.fov_width_arcmin - 77.56).abs() < 1e-2);
+        assert_eq!(geometry.pixel_size_um, 7.52);
+
+        // 2" seeing covers 2.6 px at bin 1 and 1.3 px at bin 2
+        assert_eq!(optics.sampling(1), Sampling::Optimal);
+        assert_eq!(optics.sampling(2), Sampling::Undersampled);
+        assert_eq!(optics.with_seeing(4.0).sampling(1), Sampling::Oversampled);
+        assert_eq!(optics.with_seeing(4.0).recommended_bin(&[1, 2, 3, 4]), Some(2));
+        assert_eq!(optics.with_seeing(0.5).recommended_bin(&[1, 2]), Some(1));
+
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            num_stars: 20,
+            ..Default::default()
+        }]));
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1000, 0).unwrap();
+        assert_eq!(camera.pixel_size_um().unwrap(), 4.63);
+        let optics = camera.optics(500.0).unwrap();
+        camera.set_optics(Some(optics));
+        let frame = camera.start_video_capture().unwrap().get_video_frame().unwrap();
+        assert_eq!(frame.geometry, Some(optics.geometry(&frame.roi)));
+    }
+}
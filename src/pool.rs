@@ -0,0 +1,159 @@
+use crate::BufType;
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, Weak};
+
+/// Number of idle buffers a `BufferPool` keeps by default.
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// Recycles frame buffers so that reading a frame doesn't allocate.
+///
+/// Buffers handed out by `get` go back to the pool when they are dropped.
+/// Cloning the pool gives another handle to the same buffers.
+#[derive(Clone)]
+pub struct BufferPool {
+    idle: Arc<Mutex<Vec<BufType>>>,
+    max_idle: usize,
+}
+
+impl BufferPool {
+    /// Pool keeping at most `max_idle` buffers which are not in use.
+    pub fn new(max_idle: usize) -> Self {
+        Self {
+            idle: Arc::new(Mutex::new(Vec::with_capacity(max_idle))),
+            max_idle,
+        }
+    }
+
+    /// A buffer of `len` bytes. Its content is whatever the previous user left in it.
+    pub fn get(&self, len: usize) -> PooledBuffer {
+        let idle = self.idle.lock().unwrap().pop();
+        let buf = match idle {
+            Some(mut buf) => {
+                if buf.len() != len {
+                    debug!("Resizing pooled buffer from {} to {} bytes", buf.len(), len);
+                    buf.resize(len, 0);
+                }
+                buf
+            }
+            None => {
+                debug!("Allocating buffer of {} bytes", len);
+                vec![0; len]
+            }
+        };
+        PooledBuffer {
+            buf,
+            pool: Arc::downgrade(&self.idle),
+            max_idle: self.max_idle,
+        }
+    }
+
+    /// Number of buffers waiting to be reused.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Release the idle buffers.
+    pub fn clear(&self) {
+        self.idle.lock().unwrap().clear();
+    }
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_POOL_SIZE)
+    }
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("idle", &self.idle())
+            .field("max_idle", &self.max_idle)
+            .finish()
+    }
+}
+
+/// Buffer borrowed from a `BufferPool`, returned to it on drop.
+pub struct PooledBuffer {
+    buf: BufType,
+    pool: Weak<Mutex<Vec<BufType>>>,
+    max_idle: usize,
+}
+
+impl PooledBuffer {
+    /// Take the buffer out of the pool.
+    pub fn into_vec(mut self) -> BufType {
+        self.pool = Weak::new();
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl Clone for PooledBuffer {
+    fn clone(&self) -> Self {
+        Self {
+            buf: self.buf.clone(),
+            pool: self.pool.clone(),
+            max_idle: self.max_idle,
+        }
+    }
+}
+
+impl fmt::Debug for PooledBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PooledBuffer({} bytes)", self.buf.len())
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let Some(pool) = self.pool.upgrade() else {
+            return;
+        };
+        let mut idle = pool.lock().unwrap();
+        if idle.len() < self.max_idle {
+            idle.push(std::mem::take(&mut self.buf));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BufferPool;
+
+    #[test]
+    fn test_reuse() {
+        let pool = BufferPool::new(1);
+        let buf = pool.get(16);
+        let ptr = buf.as_ptr();
+        drop(buf);
+        assert_eq!(pool.idle(), 1);
+
+        let a = pool.get(16);
+        assert_eq!(a.as_ptr(), ptr);
+        let b = pool.get(8);
+        assert_eq!(b.len(), 8);
+        drop(a);
+        // only one idle buffer is kept
+        drop(b);
+        assert_eq!(pool.idle(), 1);
+
+        let v = pool.get(16).into_vec();
+        assert_eq!(v.len(), 16);
+        assert_eq!(pool.idle(), 0);
+    }
+}
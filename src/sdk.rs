@@ -0,0 +1,182 @@
+//! Runtime loading of the SVBCameraSDK shared library (`dynamic` feature).
+//!
+//! The library is looked up on first use, in this order:
+//! 1. the path passed to `load`
+//! 2. the `SVB_SDK_PATH` environment variable (the library file or its directory)
+//! 3. the vendored `libsvb/vendor/<arch>` directory of this crate, if present at build time
+//! 4. the platform library search path
+//!
+//! A missing library or symbol is reported as an `SVBError` by the SDK call
+//! that needed it, so applications keep working without the SDK installed.
+#![allow(non_snake_case)]
+use crate::libsvb::*;
+use libloading::Library;
+use std::env;
+use std::ffi::OsStr;
+use std::os::raw::{c_char, c_int, c_long, c_uchar};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Environment variable holding the path of the SDK library or its directory.
+pub const SDK_PATH_ENV: &str = "SVB_SDK_PATH";
+
+#[cfg(target_os = "macos")]
+const LIBRARY_NAME: &str = "libSVBCameraSDK.dylib";
+#[cfg(target_os = "windows")]
+const LIBRARY_NAME: &str = "SVBCameraSDK.dll";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const LIBRARY_NAME: &str = "libSVBCameraSDK.so";
+
+macro_rules! sdk_library {
+    ($(fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        /// Function table resolved from the SVBCameraSDK shared library,
+        /// `None` for the functions an older SDK doesn't export.
+        pub struct SdkLibrary {
+            $(pub $name: Option<unsafe extern "C" fn($($ty),*) -> $ret>,)*
+            _library: Library,
+        }
+
+        impl SdkLibrary {
+            /// Load the library at `path` and resolve the SDK symbols it exports.
+            ///
+            /// # Safety
+            /// Loading a library runs its initialisers; `path` must point to the SVBCameraSDK.
+            pub unsafe fn open<P: AsRef<OsStr>>(path: P) -> Result<Self, SVBError> {
+                let path = path.as_ref();
+                let library = Library::new(path).map_err(|e| SVBError::LibraryNotLoaded {
+                    path: path.to_string_lossy().into_owned(),
+                    reason: e.to_string(),
+                })?;
+                $(
+                    let $name = library
+                        .get::<unsafe extern "C" fn($($ty),*) -> $ret>(
+                            concat!(stringify!($name), "\0").as_bytes(),
+                        )
+                        .map(|symbol| *symbol)
+                        .ok();
+                    if $name.is_none() {
+                        warn!("SVBCameraSDK at {} does not export {}", path.to_string_lossy(), stringify!($name));
+                    }
+                )*
+                Ok(Self { $($name,)* _library: library })
+            }
+        }
+    };
+}
+
+sdk_library! {
+    fn SVBGetNumOfConnectedCameras() -> c_int;
+    fn SVBGetCameraInfo(info: *mut SVB_CAMERA_INFO, camera_idx: c_int) -> c_int;
+    fn SVBGetCameraProperty(camera_id: c_int, prop: *mut SVB_CAMERA_PROPERTY) -> c_int;
+    fn SVBGetCameraPropertyEx(camera_id: c_int, prop: *mut SVB_CAMERA_PROPERTY_EX) -> c_int;
+    fn SVBOpenCamera(camera_id: c_int) -> c_int;
+    fn SVBCloseCamera(camera_id: c_int) -> c_int;
+    fn SVBGetNumOfControls(camera_id: c_int, num: *mut c_int) -> c_int;
+    fn SVBGetControlCaps(camera_id: c_int, ctl_idx: c_int, caps: *mut SVB_CONTROL_CAPS) -> c_int;
+    fn SVBGetControlValue(camera_id: c_int, ctl_type: c_int, value: *mut c_long, is_auto: *mut c_int) -> c_int;
+    fn SVBSetControlValue(camera_id: c_int, ctl_type: c_int, value: c_long, is_auto: c_int) -> c_int;
+    fn SVBGetOutputImageType(camera_id: c_int, img_type: *mut c_int) -> c_int;
+    fn SVBSetOutputImageType(camera_id: c_int, img_type: c_int) -> c_int;
+    fn SVBSetROIFormat(camera_id: c_int, startx: c_int, starty: c_int, width: c_int, height: c_int, bin: c_int) -> c_int;
+    fn SVBGetROIFormat(camera_id: c_int, startx: *mut c_int, starty: *mut c_int, width: *mut c_int, height: *mut c_int, bin: *mut c_int) -> c_int;
+    fn SVBGetDroppedFrames(camera_id: c_int, dropped: *mut c_int) -> c_int;
+    fn SVBStartVideoCapture(camera_id: c_int) -> c_int;
+    fn SVBStopVideoCapture(camera_id: c_int) -> c_int;
+    fn SVBGetVideoData(camera_id: c_int, buf: *mut c_uchar, buf_size: c_long, wait_ms: c_int) -> c_int;
+    fn SVBWhiteBalanceOnce(camera_id: c_int) -> c_int;
+    fn SVBGetCameraFirmwareVersion(camera_id: c_int, version: *mut c_char) -> c_int;
+    fn SVBGetSDKVersion() -> *const c_char;
+    fn SVBGetCameraSupportMode(camera_id: c_int, modes: *mut SVB_SUPPORTED_MODE) -> c_int;
+    fn SVBGetCameraMode(camera_id: c_int, mode: *mut SVB_CAMERA_MODE) -> c_int;
+    fn SVBSetCameraMode(camera_id: c_int, mode: SVB_CAMERA_MODE) -> c_int;
+    fn SVBSendSoftTrigger(camera_id: c_int) -> c_int;
+    fn SVBGetSerialNumber(camera_id: c_int, sn: *mut SVB_SN) -> c_int;
+    fn SVBSetTriggerOutputIOConf(camera_id: c_int, pin: SVB_TRIG_OUTPUT_PIN, pin_high: c_int, delay: c_long, duration: c_long) -> c_int;
+    fn SVBGetTriggerOutputIOConf(camera_id: c_int, pin: SVB_TRIG_OUTPUT_PIN, pin_high: *mut c_int, delay: *mut c_long, duration: *mut c_long) -> c_int;
+    fn SVBPulseGuide(camera_id: c_int, direction: c_int, duration: c_int) -> c_int;
+    fn SVBGetSensorPixelSize(camera_id: c_int, pixel_size: *mut f32) -> c_int;
+    fn SVBCanPulseGuide(camera_id: c_int, can_pulse_guide: *mut c_int) -> c_int;
+    fn SVBSetAutoSaveParam(camera_id: c_int, enable: c_int) -> c_int;
+    fn SVBIsCameraNeedToUpgrade(camera_id: c_int, need_upgrade: *mut c_int, min_version: *mut c_char) -> c_int;
+    fn SVBRestoreDefaultParam(camera_id: c_int) -> c_int;
+}
+
+static SDK: OnceLock<SdkLibrary> = OnceLock::new();
+// serialises loading so the library is opened only once
+static LOADING: Mutex<()> = Mutex::new(());
+
+/// Load the SDK from `path`, before any SDK call is made.
+///
+/// Returns `Ok` without reloading if a library has already been loaded.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<(), SVBError> {
+    let _loading = LOADING.lock().unwrap();
+    if SDK.get().is_some() {
+        warn!("SVBCameraSDK already loaded, ignoring {}", path.as_ref().display());
+        return Ok(());
+    }
+    let library = unsafe { SdkLibrary::open(path.as_ref())? };
+    let _ = SDK.set(library);
+    info!("Loaded SVBCameraSDK from {}", path.as_ref().display());
+    Ok(())
+}
+
+/// Whether the SDK has been loaded.
+pub fn is_loaded() -> bool {
+    SDK.get().is_some()
+}
+
+fn candidates() -> Vec<PathBuf> {
+    if let Some(path) = env::var_os(SDK_PATH_ENV) {
+        let path = PathBuf::from(path);
+        return if path.is_dir() {
+            vec![path.join(LIBRARY_NAME)]
+        } else {
+            vec![path]
+        };
+    }
+    let mut paths = Vec::new();
+    if let Some(dir) = option_env!("SVB_SDK_VENDOR_DIR") {
+        paths.push(Path::new(dir).join(LIBRARY_NAME));
+    }
+    paths.push(PathBuf::from(LIBRARY_NAME));
+    paths
+}
+
+/// The loaded SDK, loading it from the default locations on first use.
+pub fn library() -> Result<&'static SdkLibrary, SVBError> {
+    if let Some(library) = SDK.get() {
+        return Ok(library);
+    }
+    let _loading = LOADING.lock().unwrap();
+    if let Some(library) = SDK.get() {
+        return Ok(library);
+    }
+    let mut error = None;
+    for path in candidates() {
+        match unsafe { SdkLibrary::open(&path) } {
+            Ok(library) => {
+                info!("Loaded SVBCameraSDK from {}", path.display());
+                return Ok(SDK.get_or_init(|| library));
+            }
+            Err(e) => {
+                debug!("{}", e);
+                error = Some(e);
+            }
+        }
+    }
+    Err(error.expect("at least one candidate path"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::SdkLibrary;
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn test_missing_symbols() {
+        // any library loads, the SDK functions it lacks are left unresolved
+        let library = unsafe { SdkLibrary::open("libc.so.6") }.unwrap();
+        assert!(library.SVBGetNumOfConnectedCameras.is_none());
+        assert!(library.SVBRestoreDefaultParam.is_none());
+    }
+}
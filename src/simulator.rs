@@ -0,0 +1,1106 @@
+//! In-process simulation of an SVBONY camera.
+//!
+//! `SimulatedBackend` implements `backend::Backend` without touching the SDK:
+//! it keeps per-camera state (open/capturing, ROI, image type, controls) and
+//! renders synthetic Bayer frames of a star field with sky background, shot
+//! noise and read noise. Frames are delivered on the cadence given by the
+//! exposure and the simulated USB bandwidth, so timeouts and dropped frames
+//! behave like they do with real hardware.
+use crate::backend::Backend;
+use crate::libsvb::{self, BayerPattern, CameraMode, ControlTypeState, ImageType, ROIFormat, SVBError, TriggerOutput};
+use std::os::raw::c_char;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Static description of a simulated camera.
+#[derive(Debug, Clone)]
+pub struct SimulatedCameraConfig {
+    pub friendly_name: String,
+    pub serial_number: String,
+    pub port_type: String,
+    pub device_id: u32,
+    pub max_width: i32,
+    pub max_height: i32,
+    pub is_color: bool,
+    pub bayer_pattern: BayerPattern,
+    pub supported_bins: Vec<i32>,
+    pub supported_img_types: Vec<ImageType>,
+    pub max_bit_depth: i32,
+    /// Sensor pixel size in µm.
+    pub pixel_size_um: f32,
+    pub firmware_version: String,
+    /// Firmware the camera reports it needs an upgrade to, `None` if it's up to date.
+    pub upgrade_to_firmware: Option<String>,
+    pub is_trigger_cam: bool,
+    pub has_cooler: bool,
+    /// Whether the camera has an ST-4 guide port.
+    pub has_st4_port: bool,
+    /// Bytes per second delivered over USB, bounds the frame rate for short exposures.
+    pub bandwidth: f64,
+    /// Number of stars in the synthetic sky.
+    pub num_stars: usize,
+    /// Seed of the star field and noise generator.
+    pub seed: u64,
+}
+
+impl Default for SimulatedCameraConfig {
+    /// Modeled after an SV405CC (IMX294, 4144x2822, RGGB, cooled).
+    fn default() -> Self {
+        Self {
+            friendly_name: "SVBONY SV405CC (simulated)".to_string(),
+            serial_number: "SIM0000000000001".to_string(),
+            port_type: "USB3.0".to_string(),
+            device_id: 0x1209,
+            max_width: 4144,
+            max_height: 2822,
+            is_color: true,
+            bayer_pattern: BayerPattern::RG,
+            supported_bins: vec![1, 2, 3, 4],
+            supported_img_types: vec![
+                ImageType::Raw8,
+                ImageType::Raw16,
+                ImageType::Y8,
+                ImageType::Y16,
+                ImageType::Rgb24,
+            ],
+            max_bit_depth: 14,
+            pixel_size_um: 4.63,
+            firmware_version: "1.0.0".to_string(),
+            upgrade_to_firmware: None,
+            is_trigger_cam: false,
+            has_cooler: true,
+            has_st4_port: false,
+            bandwidth: 300.0 * 1024.0 * 1024.0,
+            num_stars: 300,
+            seed: 0x5eed_cafe,
+        }
+    }
+}
+
+/// Backend serving one or more simulated cameras.
+///
+/// The camera index and the camera id are the same number.
+#[derive(Debug)]
+pub struct SimulatedBackend {
+    cameras: Vec<Mutex<SimulatedCamera>>,
+}
+
+impl SimulatedBackend {
+    pub fn new(configs: Vec<SimulatedCameraConfig>) -> Self {
+        let cameras = configs
+            .into_iter()
+            .enumerate()
+            .map(|(id, config)| Mutex::new(SimulatedCamera::new(id as i32, config)))
+            .collect();
+        Self { cameras }
+    }
+
+    /// Lock camera `camera_id` for the SDK function `call`.
+    fn camera(&self, call: &'static str, camera_id: i32) -> Result<MutexGuard<'_, SimulatedCamera>, SVBError> {
+        usize::try_from(camera_id)
+            .ok()
+            .and_then(|id| self.cameras.get(id))
+            .map(|cam| cam.lock().unwrap())
+            .ok_or_else(|| sdk_error(call, Some(camera_id), SVBError::InvalidId))
+    }
+
+    /// Run `f` on a camera which has been opened, for the SDK function `call`.
+    fn with_open<T>(
+        &self,
+        call: &'static str,
+        camera_id: i32,
+        f: impl FnOnce(&mut SimulatedCamera) -> Result<T, SVBError>,
+    ) -> Result<T, SVBError> {
+        let mut cam = self.camera(call, camera_id)?;
+        if !cam.is_open {
+            return Err(sdk_error(call, Some(camera_id), SVBError::CameraClosed));
+        }
+        f(&mut cam).map_err(|e| sdk_error(call, Some(camera_id), e))
+    }
+}
+
+impl Default for SimulatedBackend {
+    fn default() -> Self {
+        Self::new(vec![SimulatedCameraConfig::default()])
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Star {
+    x: f32,
+    y: f32,
+    // peak brightness in ADU per second of exposure
+    flux: f32,
+    sigma: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ControlState {
+    caps: libsvb::SVB_CONTROL_CAPS,
+    value: libsvb::SVBControlValue,
+    is_auto: libsvb::SVB_BOOL,
+}
+
+/// Parameters the camera persists.
+#[derive(Debug, Clone)]
+struct Params {
+    roi: ROIFormat,
+    img_type: libsvb::SVB_IMG_TYPE,
+    controls: Vec<ControlState>,
+}
+
+impl Params {
+    fn factory(config: &SimulatedCameraConfig) -> Self {
+        let controls = default_controls(config)
+            .into_iter()
+            .map(|caps| ControlState {
+                caps,
+                value: caps.DefaultValue,
+                is_auto: libsvb::SVB_BOOL_SVB_FALSE,
+            })
+            .collect();
+        let roi = ROIFormat {
+            startx: 0,
+            starty: 0,
+            width: config.max_width,
+            height: config.max_height,
+            bin: 1,
+        };
+        Self {
+            roi,
+            img_type: config.supported_img_types[0].into(),
+            controls,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SimulatedCamera {
+    id: i32,
+    config: SimulatedCameraConfig,
+    is_open: bool,
+    capturing: bool,
+    roi: ROIFormat,
+    img_type: libsvb::SVB_IMG_TYPE,
+    controls: Vec<ControlState>,
+    stars: Vec<Star>,
+    rng: XorShift,
+    next_frame_at: Option<Instant>,
+    dropped_frames: i32,
+    mode: libsvb::SVB_CAMERA_MODE,
+    trigger_outputs: [TriggerOutput; 2],
+    /// Parameters saved when auto save was turned off, restored on close.
+    saved: Option<Params>,
+}
+
+// ambient sensor temperature in 0.1 C
+const AMBIENT_TEMPERATURE: i64 = 250;
+// the cooler can keep the sensor at most this far below ambient, in 0.1 C
+const MAX_COOLING_DELTA: i64 = 350;
+const SKY_ADU_PER_SEC: f32 = 40.0;
+const READ_NOISE_ADU: f32 = 3.0;
+
+impl SimulatedCamera {
+    fn new(id: i32, config: SimulatedCameraConfig) -> Self {
+        let mut rng = XorShift::new(config.seed);
+        let stars = (0..config.num_stars)
+            .map(|_| Star {
+                x: rng.next_f32() * config.max_width as f32,
+                y: rng.next_f32() * config.max_height as f32,
+                // log-uniform between 50 and 20000 ADU/s
+                flux: 50.0 * 400f32.powf(rng.next_f32()),
+                sigma: 1.2 + rng.next_f32(),
+            })
+            .collect();
+        let Params { roi, img_type, controls } = Params::factory(&config);
+        Self {
+            id,
+            is_open: false,
+            capturing: false,
+            roi,
+            img_type,
+            controls,
+            stars,
+            rng,
+            next_frame_at: None,
+            dropped_frames: 0,
+            mode: libsvb::SVB_CAMERA_MODE_SVB_MODE_NORMAL,
+            trigger_outputs: [TriggerOutput::default(); 2],
+            saved: None,
+            config,
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params {
+            roi: self.roi,
+            img_type: self.img_type,
+            controls: self.controls.clone(),
+        }
+    }
+
+    fn set_params(&mut self, params: Params) {
+        self.roi = params.roi;
+        self.img_type = params.img_type;
+        self.controls = params.controls;
+    }
+
+    fn control(&self, ctl_type: libsvb::SVB_CONTROL_TYPE) -> Option<&ControlState> {
+        self.controls.iter().find(|c| c.caps.ControlType == ctl_type)
+    }
+
+    fn control_value(&self, ctl_type: libsvb::SVB_CONTROL_TYPE) -> libsvb::SVBControlValue {
+        self.control(ctl_type).map_or(0, |c| c.value)
+    }
+
+    fn sensor_temperature(&self) -> i64 {
+        if self.control_value(libsvb::SVB_CONTROL_TYPE_SVB_COOLER_ENABLE) == 0 {
+            return AMBIENT_TEMPERATURE;
+        }
+        let target = self.control_value(libsvb::SVB_CONTROL_TYPE_SVB_TARGET_TEMPERATURE);
+        target.max(AMBIENT_TEMPERATURE - MAX_COOLING_DELTA)
+    }
+
+    fn cooler_power(&self) -> i64 {
+        if self.control_value(libsvb::SVB_CONTROL_TYPE_SVB_COOLER_ENABLE) == 0 {
+            return 0;
+        }
+        let delta = (AMBIENT_TEMPERATURE - self.sensor_temperature()).max(0);
+        delta * 100 / MAX_COOLING_DELTA
+    }
+
+    fn is_triggered(&self) -> bool {
+        self.mode != libsvb::SVB_CAMERA_MODE_SVB_MODE_NORMAL
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        match self.img_type {
+            libsvb::SVB_IMG_TYPE_SVB_IMG_RAW16 | libsvb::SVB_IMG_TYPE_SVB_IMG_Y16 => 2,
+            libsvb::SVB_IMG_TYPE_SVB_IMG_RGB24 => 3,
+            _ => 1,
+        }
+    }
+
+    fn frame_size(&self) -> usize {
+        self.roi.width as usize * self.roi.height as usize * self.bytes_per_pixel()
+    }
+
+    /// Time between two frames: the exposure or the USB transfer, whichever is longer.
+    fn frame_interval(&self) -> Duration {
+        let exposure = Duration::from_micros(
+            self.control_value(libsvb::SVB_CONTROL_TYPE_SVB_EXPOSURE)
+                .max(1) as u64,
+        );
+        let readout = Duration::from_secs_f64(self.frame_size() as f64 / self.config.bandwidth);
+        exposure.max(readout)
+    }
+
+    /// Relative response of the pixel at sensor position (x, y).
+    fn cfa_weight(&self, x: usize, y: usize) -> f32 {
+        if !self.config.is_color {
+            return 1.0;
+        }
+        // index into RGGB ordering, shifted for the other patterns
+        let (dx, dy) = match self.config.bayer_pattern {
+            BayerPattern::BG => (1, 1),
+            BayerPattern::GR => (1, 0),
+            BayerPattern::GB => (0, 1),
+            _ => (0, 0),
+        };
+        match ((x + dx) % 2, (y + dy) % 2) {
+            (0, 0) => CHANNEL_WEIGHTS[0],
+            (1, 1) => CHANNEL_WEIGHTS[2],
+            _ => CHANNEL_WEIGHTS[1],
+        }
+    }
+
+    /// Render the current ROI into `buf` according to the current image type.
+    fn render(&mut self, buf: &mut [u8]) {
+        let roi = self.roi;
+        let bin = roi.bin.max(1) as usize;
+        let (w, h) = (roi.width as usize, roi.height as usize);
+        // sensor area covered by the ROI
+        let (sx0, sy0) = (roi.startx as usize * bin, roi.starty as usize * bin);
+        let (sw, sh) = (w * bin, h * bin);
+
+        let exposure_s =
+            self.control_value(libsvb::SVB_CONTROL_TYPE_SVB_EXPOSURE) as f32 / 1_000_000.0;
+        // gain is in 0.1 dB
+        let gain =
+            10f32.powf(self.control_value(libsvb::SVB_CONTROL_TYPE_SVB_GAIN) as f32 / 200.0);
+        let max_adu = ((1u32 << self.config.max_bit_depth) - 1) as f32;
+        // black level is given on an 8 bit scale
+        let offset = self.control_value(libsvb::SVB_CONTROL_TYPE_SVB_BLACK_LEVEL) as f32
+            * (max_adu + 1.0)
+            / 256.0;
+
+        // photo signal in ADU over the sensor area
+        let mut signal = vec![SKY_ADU_PER_SEC * exposure_s; sw * sh];
+        for star in &self.stars {
+            let reach = star.sigma * 4.0;
+            let x0 = (star.x - reach).max(sx0 as f32) as usize;
+            let y0 = (star.y - reach).max(sy0 as f32) as usize;
+            let x1 = ((star.x + reach).ceil() as usize).min(sx0 + sw);
+            let y1 = ((star.y + reach).ceil() as usize).min(sy0 + sh);
+            let peak = star.flux * exposure_s;
+            let denom = 2.0 * star.sigma * star.sigma;
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let (dx, dy) = (x as f32 + 0.5 - star.x, y as f32 + 0.5 - star.y);
+                    signal[(y - sy0) * sw + (x - sx0)] += peak * (-(dx * dx + dy * dy) / denom).exp();
+                }
+            }
+        }
+
+        let rgb = self.img_type == libsvb::SVB_IMG_TYPE_SVB_IMG_RGB24;
+        let mono = rgb
+            || self.img_type == libsvb::SVB_IMG_TYPE_SVB_IMG_Y8
+            || self.img_type == libsvb::SVB_IMG_TYPE_SVB_IMG_Y16;
+        let flip = self.control_value(libsvb::SVB_CONTROL_TYPE_SVB_FLIP);
+        let bpp = self.bytes_per_pixel();
+        let depth = self.config.max_bit_depth as u32;
+
+        for oy in 0..h {
+            for ox in 0..w {
+                let mut sum = 0.0;
+                for by in 0..bin {
+                    for bx in 0..bin {
+                        let (x, y) = (ox * bin + bx, oy * bin + by);
+                        let weight = if mono {
+                            1.0
+                        } else {
+                            self.cfa_weight(sx0 + x, sy0 + y)
+                        };
+                        let s = signal[y * sw + x] * weight * gain;
+                        let noise = self.rng.next_gaussian()
+                            * (s.max(0.0) + READ_NOISE_ADU * READ_NOISE_ADU).sqrt();
+                        sum += s + noise;
+                    }
+                }
+                let adu = (sum / (bin * bin) as f32 + offset).clamp(0.0, max_adu);
+
+                let tx = if flip & 1 != 0 { w - 1 - ox } else { ox };
+                let ty = if flip & 2 != 0 { h - 1 - oy } else { oy };
+                let i = (ty * w + tx) * bpp;
+                match bpp {
+                    2 => {
+                        let v = (adu as u32) << (16 - depth);
+                        buf[i..i + 2].copy_from_slice(&(v as u16).to_le_bytes());
+                    }
+                    3 => {
+                        let v = adu / max_adu * 255.0;
+                        for (c, weight) in CHANNEL_WEIGHTS.iter().enumerate() {
+                            buf[i + c] = (v * weight).min(255.0) as u8;
+                        }
+                    }
+                    _ => buf[i] = ((adu as u32) >> (depth - 8)) as u8,
+                }
+            }
+        }
+    }
+}
+
+// relative sensitivity of the red, green and blue pixels
+const CHANNEL_WEIGHTS: [f32; 3] = [0.8, 1.0, 0.6];
+
+/// `source` wrapped in `SVBError::Call` like the errors of the SDK, so code
+/// matching on `root()` behaves the same on both backends.
+fn sdk_error(call: &'static str, camera_id: Option<i32>, source: SVBError) -> SVBError {
+    SVBError::Call {
+        call,
+        camera_id,
+        source: Box::new(source),
+    }
+}
+
+fn fill_c_str(dst: &mut [c_char], src: &str) {
+    let len = dst.len() - 1;
+    for (d, s) in dst.iter_mut().zip(src.bytes().take(len)) {
+        *d = s as c_char;
+    }
+}
+
+fn control_caps(
+    ctl_type: libsvb::SVB_CONTROL_TYPE,
+    name: &str,
+    description: &str,
+    (min, max, default): (i64, i64, i64),
+    is_auto_supported: bool,
+    is_writable: bool,
+) -> libsvb::SVB_CONTROL_CAPS {
+    let mut caps = libsvb::SVB_CONTROL_CAPS::new();
+    fill_c_str(&mut caps.Name, name);
+    fill_c_str(&mut caps.Description, description);
+    caps.MinValue = min as _;
+    caps.MaxValue = max as _;
+    caps.DefaultValue = default as _;
+    caps.IsAutoSupported = is_auto_supported as libsvb::SVB_BOOL;
+    caps.IsWritable = is_writable as libsvb::SVB_BOOL;
+    caps.ControlType = ctl_type;
+    caps
+}
+
+fn default_controls(config: &SimulatedCameraConfig) -> Vec<libsvb::SVB_CONTROL_CAPS> {
+    let mut controls = vec![
+        control_caps(libsvb::SVB_CONTROL_TYPE_SVB_GAIN, "Gain", "Gain", (0, 720, 10), true, true),
+        control_caps(
+            libsvb::SVB_CONTROL_TYPE_SVB_EXPOSURE,
+            "Exposure",
+            "Exposure Time(us)",
+            (29, 2_000_000_000, 10_000),
+            true,
+            true,
+        ),
+        control_caps(libsvb::SVB_CONTROL_TYPE_SVB_GAMMA, "Gamma", "Gamma", (0, 1000, 100), false, true),
+        control_caps(libsvb::SVB_CONTROL_TYPE_SVB_FLIP, "Flip", "Flip", (0, 3, 0), false, true),
+        control_caps(
+            libsvb::SVB_CONTROL_TYPE_SVB_FRAME_SPEED_MODE,
+            "FrameSpeed",
+            "Frame speed mode",
+            (0, 2, 2),
+            false,
+            true,
+        ),
+        control_caps(libsvb::SVB_CONTROL_TYPE_SVB_CONTRAST, "Contrast", "Contrast", (0, 100, 50), false, true),
+        control_caps(libsvb::SVB_CONTROL_TYPE_SVB_SHARPNESS, "Sharpness", "Sharpness", (0, 100, 0), false, true),
+        control_caps(
+            libsvb::SVB_CONTROL_TYPE_SVB_BLACK_LEVEL,
+            "Offset",
+            "Black level offset",
+            (0, 255, 10),
+            false,
+            true,
+        ),
+        control_caps(
+            libsvb::SVB_CONTROL_TYPE_SVB_BAD_PIXEL_CORRECTION_ENABLE,
+            "BadPixelCorrection",
+            "Bad pixel correction",
+            (0, 1, 1),
+            false,
+            true,
+        ),
+    ];
+    if config.is_color {
+        controls.extend([
+            control_caps(libsvb::SVB_CONTROL_TYPE_SVB_WB_R, "WB_R", "White balance red", (0, 1000, 128), true, true),
+            control_caps(libsvb::SVB_CONTROL_TYPE_SVB_WB_G, "WB_G", "White balance green", (0, 1000, 128), false, true),
+            control_caps(libsvb::SVB_CONTROL_TYPE_SVB_WB_B, "WB_B", "White balance blue", (0, 1000, 128), true, true),
+            control_caps(libsvb::SVB_CONTROL_TYPE_SVB_SATURATION, "Saturation", "Saturation", (0, 255, 128), false, true),
+        ]);
+    }
+    if config.has_cooler {
+        controls.extend([
+            control_caps(
+                libsvb::SVB_CONTROL_TYPE_SVB_COOLER_ENABLE,
+                "CoolerEnable",
+                "Cooler enable",
+                (0, 1, 0),
+                false,
+                true,
+            ),
+            control_caps(
+                libsvb::SVB_CONTROL_TYPE_SVB_TARGET_TEMPERATURE,
+                "TargetTemp",
+                "Target temperature(0.1C)",
+                (-350, 300, 0),
+                false,
+                true,
+            ),
+            control_caps(
+                libsvb::SVB_CONTROL_TYPE_SVB_CURRENT_TEMPERATURE,
+                "Temperature",
+                "Sensor temperature(0.1C)",
+                (-500, 1000, AMBIENT_TEMPERATURE),
+                false,
+                false,
+            ),
+            control_caps(
+                libsvb::SVB_CONTROL_TYPE_SVB_COOLER_POWER,
+                "CoolerPower",
+                "Cooler power(%)",
+                (0, 100, 0),
+                false,
+                false,
+            ),
+        ]);
+    }
+    controls
+}
+
+impl Backend for SimulatedBackend {
+    fn get_num_of_connected_cameras(&self) -> Result<i32, SVBError> {
+        Ok(self.cameras.len() as i32)
+    }
+
+    fn get_camera_info(&self, camera_idx: i32) -> Result<libsvb::SVB_CAMERA_INFO, SVBError> {
+        let cam = usize::try_from(camera_idx)
+            .ok()
+            .and_then(|idx| self.cameras.get(idx))
+            .ok_or_else(|| sdk_error("SVBGetCameraInfo", None, SVBError::InvalidIndex))?
+            .lock()
+            .unwrap();
+        let mut info = libsvb::SVB_CAMERA_INFO::new();
+        fill_c_str(&mut info.FriendlyName, &cam.config.friendly_name);
+        fill_c_str(&mut info.CameraSN, &cam.config.serial_number);
+        fill_c_str(&mut info.PortType, &cam.config.port_type);
+        info.DeviceID = cam.config.device_id;
+        info.CameraID = cam.id;
+        Ok(info)
+    }
+
+    fn get_camera_prop(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_PROPERTY, SVBError> {
+        let cam = self.camera("SVBGetCameraProperty", camera_id)?;
+        let config = &cam.config;
+        let mut prop = libsvb::SVB_CAMERA_PROPERTY::new();
+        prop.MaxWidth = config.max_width as _;
+        prop.MaxHeight = config.max_height as _;
+        prop.IsColorCam = config.is_color as libsvb::SVB_BOOL;
+        prop.BayerPattern = config.bayer_pattern.into();
+        prop.MaxBitDepth = config.max_bit_depth;
+        prop.IsTriggerCam = config.is_trigger_cam as libsvb::SVB_BOOL;
+        for (dst, bin) in prop.SupportedBins.iter_mut().zip(&config.supported_bins) {
+            *dst = *bin;
+        }
+        prop.SupportedVideoFormat = [libsvb::SVB_IMG_TYPE_SVB_IMG_END; 8];
+        for (dst, t) in prop
+            .SupportedVideoFormat
+            .iter_mut()
+            .zip(&config.supported_img_types)
+        {
+            *dst = (*t).into();
+        }
+        Ok(prop)
+    }
+
+    fn get_camera_prop_ex(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_PROPERTY_EX, SVBError> {
+        let cam = self.camera("SVBGetCameraPropertyEx", camera_id)?;
+        let mut prop_ex = libsvb::SVB_CAMERA_PROPERTY_EX::new();
+        prop_ex.bSupportPulseGuide = cam.config.has_st4_port as libsvb::SVB_BOOL;
+        prop_ex.bSupportControlTemp = cam.config.has_cooler as libsvb::SVB_BOOL;
+        Ok(prop_ex)
+    }
+
+    fn get_firmware_version(&self, camera_id: i32) -> Result<String, SVBError> {
+        self.with_open("SVBGetCameraFirmwareVersion", camera_id, |cam| Ok(cam.config.firmware_version.clone()))
+    }
+
+    fn get_sdk_version(&self) -> Result<String, SVBError> {
+        Ok(concat!("svbony-camera-rs simulator ", env!("CARGO_PKG_VERSION")).to_string())
+    }
+
+    fn is_camera_need_to_upgrade(&self, camera_id: i32) -> Result<(bool, String), SVBError> {
+        self.with_open("SVBIsCameraNeedToUpgrade", camera_id, |cam| match &cam.config.upgrade_to_firmware {
+            Some(version) => Ok((true, version.clone())),
+            None => Ok((false, String::new())),
+        })
+    }
+
+    fn get_sensor_pixel_size(&self, camera_id: i32) -> Result<f32, SVBError> {
+        let cam = self.camera("SVBGetSensorPixelSize", camera_id)?;
+        Ok(cam.config.pixel_size_um)
+    }
+
+    fn open_camera(&self, camera_id: i32) -> Result<(), SVBError> {
+        let mut cam = self.camera("SVBOpenCamera", camera_id)?;
+        cam.is_open = true;
+        Ok(())
+    }
+
+    fn close_camera(&self, camera_id: i32) -> Result<(), SVBError> {
+        let mut cam = self.camera("SVBCloseCamera", camera_id)?;
+        cam.is_open = false;
+        cam.capturing = false;
+        cam.next_frame_at = None;
+        if let Some(saved) = cam.saved.clone() {
+            cam.set_params(saved);
+        }
+        Ok(())
+    }
+
+    fn get_num_of_controls(&self, camera_id: i32) -> Result<i32, SVBError> {
+        self.with_open("SVBGetNumOfControls", camera_id, |cam| Ok(cam.controls.len() as i32))
+    }
+
+    fn get_ctl_caps(
+        &self,
+        camera_id: i32,
+        ctl_idx: i32,
+    ) -> Result<libsvb::SVB_CONTROL_CAPS, SVBError> {
+        self.with_open("SVBGetControlCaps", camera_id, |cam| {
+            usize::try_from(ctl_idx)
+                .ok()
+                .and_then(|idx| cam.controls.get(idx))
+                .map(|c| c.caps)
+                .ok_or(SVBError::InvalidControlType)
+        })
+    }
+
+    fn get_ctl_value(
+        &self,
+        camera_id: i32,
+        ctl_type: libsvb::SVB_CONTROL_TYPE,
+    ) -> Result<ControlTypeState, SVBError> {
+        self.with_open("SVBGetControlValue", camera_id, |cam| {
+            let ctl = cam.control(ctl_type).ok_or(SVBError::InvalidControlType)?;
+            let value = match ctl_type {
+                libsvb::SVB_CONTROL_TYPE_SVB_CURRENT_TEMPERATURE => cam.sensor_temperature(),
+                libsvb::SVB_CONTROL_TYPE_SVB_COOLER_POWER => cam.cooler_power(),
+                _ => ctl.value,
+            };
+            Ok(ControlTypeState {
+                value,
+                is_auto: ctl.is_auto as i32,
+            })
+        })
+    }
+
+    fn set_ctl_value(
+        &self,
+        camera_id: i32,
+        ctl_type: libsvb::SVB_CONTROL_TYPE,
+        value: libsvb::SVBControlValue,
+        is_auto: libsvb::SVB_BOOL,
+    ) -> Result<(), SVBError> {
+        self.with_open("SVBSetControlValue", camera_id, |cam| {
+            let ctl = cam
+                .controls
+                .iter_mut()
+                .find(|c| c.caps.ControlType == ctl_type)
+                .ok_or(SVBError::InvalidControlType)?;
+            if ctl.caps.IsWritable == 0 {
+                return Err(SVBError::GeneralError);
+            }
+            // like the SDK, out of range values are clamped instead of refused
+            ctl.value = value.clamp(ctl.caps.MinValue as _, ctl.caps.MaxValue as _);
+            ctl.is_auto = is_auto & ctl.caps.IsAutoSupported;
+            Ok(())
+        })
+    }
+
+    fn get_roi_format(&self, camera_id: i32) -> Result<ROIFormat, SVBError> {
+        self.with_open("SVBGetROIFormat", camera_id, |cam| Ok(cam.roi))
+    }
+
+    fn set_roi_format(&self, camera_id: i32, roi: ROIFormat) -> Result<(), SVBError> {
+        self.with_open("SVBSetROIFormat", camera_id, |cam| {
+            if cam.capturing {
+                return Err(SVBError::VideoModeActive);
+            }
+            let config = &cam.config;
+            if !config.supported_bins.contains(&roi.bin)
+                || roi.width <= 0
+                || roi.height <= 0
+                || roi.width % 8 != 0
+                || roi.height % 2 != 0
+                || roi.width * roi.bin > config.max_width
+                || roi.height * roi.bin > config.max_height
+            {
+                return Err(SVBError::InvalidSize);
+            }
+            if roi.startx < 0
+                || roi.starty < 0
+                || (roi.startx + roi.width) * roi.bin > config.max_width
+                || (roi.starty + roi.height) * roi.bin > config.max_height
+            {
+                return Err(SVBError::OutOfBoundary);
+            }
+            cam.roi = roi;
+            Ok(())
+        })
+    }
+
+    fn get_img_type(&self, camera_id: i32) -> Result<libsvb::SVB_IMG_TYPE, SVBError> {
+        self.with_open("SVBGetOutputImageType", camera_id, |cam| Ok(cam.img_type))
+    }
+
+    fn set_img_type(&self, camera_id: i32, img_type: libsvb::SVB_IMG_TYPE) -> Result<(), SVBError> {
+        self.with_open("SVBSetOutputImageType", camera_id, |cam| {
+            if cam.capturing {
+                return Err(SVBError::VideoModeActive);
+            }
+            if !cam
+                .config
+                .supported_img_types
+                .iter()
+                .any(|&t| i32::from(t) == img_type)
+            {
+                return Err(SVBError::InvalidImgType);
+            }
+            cam.img_type = img_type;
+            Ok(())
+        })
+    }
+
+    fn start_video_capture(&self, camera_id: i32) -> Result<(), SVBError> {
+        self.with_open("SVBStartVideoCapture", camera_id, |cam| {
+            if !cam.capturing {
+                cam.capturing = true;
+                // in trigger mode exposures only start on a trigger
+                cam.next_frame_at = match cam.is_triggered() {
+                    true => None,
+                    false => Some(Instant::now() + cam.frame_interval()),
+                };
+            }
+            Ok(())
+        })
+    }
+
+    fn stop_video_capture(&self, camera_id: i32) -> Result<(), SVBError> {
+        self.with_open("SVBStopVideoCapture", camera_id, |cam| {
+            cam.capturing = false;
+            cam.next_frame_at = None;
+            cam.dropped_frames = 0;
+            Ok(())
+        })
+    }
+
+    fn get_video_data(
+        &self,
+        camera_id: i32,
+        buf: &mut [u8],
+        wait_ms: i32,
+    ) -> Result<(), SVBError> {
+        let now = Instant::now();
+        let due = self.with_open("SVBGetVideoData", camera_id, |cam| {
+            if !cam.capturing {
+                return Err(SVBError::InvalidSequence);
+            }
+            if buf.len() < cam.frame_size() {
+                return Err(SVBError::BufferTooSmall);
+            }
+            let Some(mut due) = cam.next_frame_at else {
+                // waiting for a trigger
+                return Ok(None);
+            };
+            // frames completed while nobody was reading are overwritten
+            let interval = cam.frame_interval();
+            if !cam.is_triggered() && now > due + interval {
+                let missed = ((now - due).as_nanos() / interval.as_nanos()) as u32;
+                cam.dropped_frames += missed as i32;
+                due += interval * missed;
+                cam.next_frame_at = Some(due);
+            }
+            Ok(Some(due))
+        })?;
+
+        let Some(due) = due else {
+            if wait_ms >= 0 {
+                thread::sleep(Duration::from_millis(wait_ms as u64));
+            }
+            return Err(sdk_error("SVBGetVideoData", Some(camera_id), SVBError::Timeout));
+        };
+        if wait_ms >= 0 {
+            let deadline = now + Duration::from_millis(wait_ms as u64);
+            if due > deadline {
+                thread::sleep(deadline - now);
+                return Err(sdk_error("SVBGetVideoData", Some(camera_id), SVBError::Timeout));
+            }
+        }
+        if let Some(remaining) = due.checked_duration_since(Instant::now()) {
+            thread::sleep(remaining);
+        }
+
+        self.with_open("SVBGetVideoData", camera_id, |cam| {
+            // capture may have been stopped or reconfigured while waiting
+            if !cam.capturing || buf.len() < cam.frame_size() {
+                return Err(SVBError::InvalidSequence);
+            }
+            cam.render(buf);
+            cam.next_frame_at = match cam.is_triggered() {
+                true => None,
+                false => Some(due + cam.frame_interval()),
+            };
+            Ok(())
+        })
+    }
+
+    fn get_serial_number(&self, camera_id: i32) -> Result<libsvb::SVB_SN, SVBError> {
+        self.with_open("SVBGetSerialNumber", camera_id, |cam| {
+            let mut sn = libsvb::SVB_SN::new();
+            for (d, s) in sn.id.iter_mut().zip(cam.config.serial_number.bytes()) {
+                *d = s;
+            }
+            Ok(sn)
+        })
+    }
+
+    fn get_dropped_frames(&self, camera_id: i32) -> Result<i32, SVBError> {
+        self.with_open("SVBGetDroppedFrames", camera_id, |cam| Ok(cam.dropped_frames))
+    }
+
+    fn adjust_white_balance(&self, camera_id: i32) -> Result<(), SVBError> {
+        self.with_open("SVBWhiteBalanceOnce", camera_id, |cam| {
+            if !cam.config.is_color {
+                return Err(SVBError::GeneralError);
+            }
+            // set gains which neutralise the simulated channel response
+            for (ctl_type, weight) in [
+                (libsvb::SVB_CONTROL_TYPE_SVB_WB_R, CHANNEL_WEIGHTS[0]),
+                (libsvb::SVB_CONTROL_TYPE_SVB_WB_G, CHANNEL_WEIGHTS[1]),
+                (libsvb::SVB_CONTROL_TYPE_SVB_WB_B, CHANNEL_WEIGHTS[2]),
+            ] {
+                if let Some(ctl) = cam.controls.iter_mut().find(|c| c.caps.ControlType == ctl_type) {
+                    ctl.value = (128.0 / weight) as libsvb::SVBControlValue;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn can_pulse_guide(&self, camera_id: i32) -> Result<bool, SVBError> {
+        self.with_open("SVBCanPulseGuide", camera_id, |cam| Ok(cam.config.has_st4_port))
+    }
+
+    fn pulse_guide(
+        &self,
+        camera_id: i32,
+        direction: libsvb::SVB_GUIDE_DIRECTION,
+        duration: i32,
+    ) -> Result<(), SVBError> {
+        self.with_open("SVBPulseGuide", camera_id, |cam| {
+            if !cam.config.has_st4_port || duration < 0 {
+                return Err(SVBError::GeneralError);
+            }
+            libsvb::GuideDirection::try_from(direction).map_err(|_| SVBError::InvalidDirection)?;
+            Ok(())
+        })?;
+        // modeled as blocking for the length of the pulse
+        thread::sleep(Duration::from_millis(duration as u64));
+        Ok(())
+    }
+
+    fn set_auto_save_param(&self, camera_id: i32, enable: bool) -> Result<(), SVBError> {
+        self.with_open("SVBSetAutoSaveParam", camera_id, |cam| {
+            cam.saved = match enable {
+                true => None,
+                false => Some(cam.saved.take().unwrap_or_else(|| cam.params())),
+            };
+            Ok(())
+        })
+    }
+
+    fn restore_default_param(&self, camera_id: i32) -> Result<(), SVBError> {
+        self.with_open("SVBRestoreDefaultParam", camera_id, |cam| {
+            if cam.capturing {
+                return Err(SVBError::InvalidSequence);
+            }
+            let params = Params::factory(&cam.config);
+            cam.set_params(params);
+            Ok(())
+        })
+    }
+
+    fn get_trigger_output_io_conf(
+        &self,
+        camera_id: i32,
+        pin: libsvb::SVB_TRIG_OUTPUT_PIN,
+    ) -> Result<TriggerOutput, SVBError> {
+        self.with_open("SVBGetTriggerOutputIOConf", camera_id, |cam| {
+            let output = cam.trigger_outputs.get(pin as usize).ok_or(SVBError::GeneralError)?;
+            Ok(*output)
+        })
+    }
+
+    fn set_trigger_output_io_conf(
+        &self,
+        camera_id: i32,
+        pin: libsvb::SVB_TRIG_OUTPUT_PIN,
+        output: TriggerOutput,
+    ) -> Result<(), SVBError> {
+        self.with_open("SVBSetTriggerOutputIOConf", camera_id, |cam| {
+            if output.delay > TriggerOutput::MAX_TIME || output.duration > TriggerOutput::MAX_TIME {
+                return Err(SVBError::GeneralError);
+            }
+            let slot = cam.trigger_outputs.get_mut(pin as usize).ok_or(SVBError::GeneralError)?;
+            *slot = output;
+            Ok(())
+        })
+    }
+
+    fn get_camera_support_mode(&self, camera_id: i32) -> Result<libsvb::SVB_SUPPORTED_MODE, SVBError> {
+        self.with_open("SVBGetCameraSupportMode", camera_id, |cam| {
+            let mut modes = libsvb::SVB_SUPPORTED_MODE::new();
+            let supported: &[CameraMode] = match cam.config.is_trigger_cam {
+                true => CameraMode::ALL,
+                false => &[CameraMode::Normal],
+            };
+            for (dst, &mode) in modes.SupportedCameraMode.iter_mut().zip(supported) {
+                *dst = mode.into();
+            }
+            Ok(modes)
+        })
+    }
+
+    fn get_camera_mode(&self, camera_id: i32) -> Result<libsvb::SVB_CAMERA_MODE, SVBError> {
+        self.with_open("SVBGetCameraMode", camera_id, |cam| Ok(cam.mode))
+    }
+
+    fn set_camera_mode(&self, camera_id: i32, mode: libsvb::SVB_CAMERA_MODE) -> Result<(), SVBError> {
+        self.with_open("SVBSetCameraMode", camera_id, |cam| {
+            if cam.capturing {
+                return Err(SVBError::InvalidSequence);
+            }
+            let supported = match cam.config.is_trigger_cam {
+                true => CameraMode::try_from(mode).is_ok(),
+                false => mode == libsvb::SVB_CAMERA_MODE_SVB_MODE_NORMAL,
+            };
+            if !supported {
+                return Err(SVBError::InvalidMode);
+            }
+            cam.mode = mode;
+            Ok(())
+        })
+    }
+
+    fn send_soft_trigger(&self, camera_id: i32) -> Result<(), SVBError> {
+        self.with_open("SVBSendSoftTrigger", camera_id, |cam| {
+            if cam.mode != libsvb::SVB_CAMERA_MODE_SVB_MODE_TRIG_SOFT {
+                return Err(SVBError::InvalidMode);
+            }
+            if !cam.capturing {
+                return Err(SVBError::InvalidSequence);
+            }
+            if cam.next_frame_at.is_none() {
+                cam.next_frame_at = Some(Instant::now() + cam.frame_interval());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// xorshift64* generator, good enough for noise and star positions.
+#[derive(Debug, Clone)]
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in [0, 1).
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Standard normal sample (Box-Muller).
+    fn next_gaussian(&mut self) -> f32 {
+        let u1 = self.next_f32().max(f32::MIN_POSITIVE);
+        let u2 = self.next_f32();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SimulatedBackend, SimulatedCameraConfig};
+    use crate::backend::Backend;
+    use crate::camera::Camera;
+    use crate::libsvb::{self, ControlType, ImageType, ROIFormat, SVBError};
+    use std::sync::Arc;
+
+    fn small_config() -> SimulatedCameraConfig {
+        SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            num_stars: 20,
+            ..Default::default()
+        }
+    }
+
+    fn camera() -> Camera {
+        let backend = Arc::new(SimulatedBackend::new(vec![small_config()]));
+        let camera = Camera::with_backend(0, backend).init().unwrap();
+        camera
+            .set_ctl_value(ControlType::Exposure, 1000, 0)
+            .unwrap();
+        camera
+    }
+
+    #[test]
+    fn test_capture_frame() {
+        let mut camera = camera();
+        camera.set_roi_format(8, 4, 160, 120, 1).unwrap();
+        camera.set_img_type(ImageType::Raw16).unwrap();
+        let session = camera.start_video_capture().unwrap();
+        let buf = session.get_video_frame().unwrap().data;
+        session.stop().unwrap();
+        camera.close().unwrap();
+
+        assert_eq!(buf.len(), 160 * 120 * 2);
+        let pixels: Vec<u16> = buf
+            .chunks(2)
+            .map(|p| u16::from_le_bytes([p[0], p[1]]))
+            .collect();
+        // black level offset of 10 on the 8 bit scale plus noise
+        let mean = pixels.iter().map(|&p| p as f64).sum::<f64>() / pixels.len() as f64;
+        assert!((mean - 10.0 * 256.0).abs() < 100.0, "mean {}", mean);
+        assert!(pixels.iter().any(|&p| p != pixels[0]));
+    }
+
+    #[test]
+    fn test_binning() {
+        let mut camera = camera();
+        camera.set_roi_format(0, 0, 160, 120, 2).unwrap();
+        let session = camera.start_video_capture().unwrap();
+        let buf = session.get_video_frame().unwrap().data;
+        session.stop().unwrap();
+        assert_eq!(buf.len(), 160 * 120);
+    }
+
+    #[test]
+    fn test_invalid_roi() {
+        let backend = SimulatedBackend::new(vec![small_config()]);
+        backend.open_camera(0).unwrap();
+        let roi = |startx, width, bin| ROIFormat {
+            startx,
+            starty: 0,
+            width,
+            height: 120,
+            bin,
+        };
+        assert!(matches!(
+            backend.set_roi_format(0, roi(0, 164, 1)),
+            Err(e) if matches!(e.root(), SVBError::InvalidSize)
+        ));
+        assert!(matches!(
+            backend.set_roi_format(0, roi(0, 320, 5)),
+            Err(e) if matches!(e.root(), SVBError::InvalidSize)
+        ));
+        assert!(matches!(
+            backend.set_roi_format(0, roi(100, 160, 2)),
+            Err(e) if matches!(e.root(), SVBError::OutOfBoundary)
+        ));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let backend = SimulatedBackend::new(vec![small_config()]);
+        backend.open_camera(0).unwrap();
+        backend
+            .set_ctl_value(0, libsvb::SVB_CONTROL_TYPE_SVB_EXPOSURE, 2_000_000, 0)
+            .unwrap();
+        backend.start_video_capture(0).unwrap();
+        let mut buf = vec![0; 320 * 240];
+        assert!(matches!(
+            backend.get_video_data(0, &mut buf, 10),
+            Err(e) if matches!(e.root(), SVBError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn test_closed_camera() {
+        let backend = SimulatedBackend::default();
+        let e = backend.start_video_capture(0).unwrap_err();
+        assert!(matches!(e.root(), SVBError::CameraClosed));
+        assert_eq!((e.call(), e.camera_id()), (Some("SVBStartVideoCapture"), Some(0)));
+        assert!(matches!(backend.get_camera_info(1), Err(e) if matches!(e.root(), SVBError::InvalidIndex)));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::libsvb::ExposureStatus;
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often `Camera::snap` reports progress and checks for cancellation.
+pub const SNAP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Cancels a running `Camera::snap` from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress of a `Camera::snap`, passed to `SnapOptions::progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapProgress {
+    pub status: ExposureStatus,
+    /// Time since the exposure was started.
+    pub elapsed: Duration,
+    pub exposure: Duration,
+}
+
+impl SnapProgress {
+    /// Exposure time left, zero while the frame is being downloaded.
+    pub fn remaining(&self) -> Duration {
+        self.exposure.saturating_sub(self.elapsed)
+    }
+}
+
+/// Callback receiving the progress of a `Camera::snap`.
+pub type ProgressCallback = Box<dyn FnMut(&SnapProgress) + Send>;
+
+/// How `Camera::snap` takes its frame.
+#[derive(Default)]
+pub struct SnapOptions {
+    /// Called with `Idle` before the exposure starts, with `Working` every
+    /// `SNAP_POLL_INTERVAL` while exposing and downloading, and with `Success`
+    /// or `Failed` at the end.
+    pub progress: Option<ProgressCallback>,
+    /// Aborts the exposure with `SVBError::Cancelled`.
+    pub cancel: Option<CancelToken>,
+}
+
+impl fmt::Debug for SnapOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SnapOptions")
+            .field("progress", &self.progress.is_some())
+            .field("cancel", &self.cancel)
+            .finish()
+    }
+}
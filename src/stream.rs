@@ -0,0 +1,334 @@
+use crate::camera::{Camera, FrameReader};
+use crate::frame::{CaptureStats, Frame};
+use crate::libsvb::SVBError;
+
+use std::collections::VecDeque;
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// What the capture thread does with a new frame when the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued frame to make room.
+    #[default]
+    DropOldest,
+    /// Drop the new frame.
+    DropNewest,
+    /// Wait until a frame is taken from the queue. Frames the SDK delivers
+    /// meanwhile are discarded by the SDK and show up as dropped frames.
+    Block,
+}
+
+/// How `Camera::start_stream` queues frames.
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// Number of frames the queue holds, at least 1.
+    pub queue_size: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            queue_size: 4,
+            overflow: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+/// Counters of a `FrameStream`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    /// Frames read from the SDK, timeouts and frames the SDK dropped because
+    /// the thread didn't read them in time.
+    pub capture: CaptureStats,
+    /// Frames or errors dropped because the queue was full.
+    pub overflows: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    queue: VecDeque<Result<Frame, SVBError>>,
+    stats: StreamStats,
+    stop: bool,
+    finished: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+/// Video capture whose frames are read by a background thread, see
+/// `Camera::start_stream`.
+///
+/// The SDK discards frames which aren't read as fast as they arrive, so the
+/// thread only reads frames into a bounded queue, which is drained by
+/// iterating over the stream. Iterating blocks until a frame or an error is
+/// available; timeouts are passed on and the thread keeps reading, any other
+/// error ends the stream. Like `CaptureSession`, the stream dereferences to
+/// the camera for changing controls and stops the capture when dropped.
+#[derive(Debug)]
+pub struct FrameStream<'a> {
+    camera: &'a mut Camera,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+    is_capturing: bool,
+}
+
+impl<'a> FrameStream<'a> {
+    pub(crate) fn start(
+        camera: &'a mut Camera,
+        reader: FrameReader,
+        options: StreamOptions,
+    ) -> Result<Self, SVBError> {
+        let shared = Arc::new(Shared::default());
+        let thread = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name(format!("svb-capture-{}", camera.id))
+                .spawn(move || capture_loop(reader, options, shared))
+                .map_err(|e| {
+                    error!("{} : Failed to spawn capture thread", e);
+                    SVBError::GeneralError
+                })?
+        };
+        Ok(FrameStream {
+            camera,
+            shared,
+            thread: Some(thread),
+            is_capturing: true,
+        })
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        self.shared.state.lock().unwrap().stats
+    }
+
+    /// Like `next`, but gives up after `timeout`.
+    ///
+    /// `None` means that no frame arrived in time or that the stream has
+    /// ended, which `is_finished` tells apart.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Option<Result<Frame, SVBError>> {
+        let state = self.shared.state.lock().unwrap();
+        let (mut state, _) = self
+            .shared
+            .not_empty
+            .wait_timeout_while(state, timeout, |state| state.queue.is_empty() && !state.finished)
+            .unwrap();
+        let item = state.queue.pop_front();
+        drop(state);
+        if item.is_some() {
+            self.shared.not_full.notify_one();
+        }
+        item
+    }
+
+    /// Whether the capture thread has stopped and every frame has been taken.
+    pub fn is_finished(&self) -> bool {
+        let state = self.shared.state.lock().unwrap();
+        state.finished && state.queue.is_empty()
+    }
+
+    /// Stop the capture, reporting the error dropping the stream would only log.
+    pub fn stop(mut self) -> Result<StreamStats, SVBError> {
+        self.join();
+        self.is_capturing = false;
+        self.camera.stop_video_capture()?;
+        Ok(self.stats())
+    }
+
+    fn join(&mut self) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.stop = true;
+        }
+        self.shared.not_full.notify_all();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Capture thread of camera_id {} panicked", self.camera.id);
+            }
+        }
+    }
+}
+
+fn capture_loop(reader: FrameReader, options: StreamOptions, shared: Arc<Shared>) {
+    let queue_size = options.queue_size.max(1);
+    let mut sequence = 0;
+    loop {
+        if shared.state.lock().unwrap().stop {
+            break;
+        }
+        // checks for `stop` while waiting, a long exposure would hold up
+        // stopping the stream otherwise
+        let item = reader.read_frame_polled(sequence, &mut || shared.state.lock().unwrap().stop);
+        if matches!(&item, Err(SVBError::Cancelled)) {
+            break;
+        }
+        let fatal = match &item {
+            Ok(_) => {
+                sequence += 1;
+                false
+            }
+            Err(e) => !matches!(e.root(), SVBError::Timeout),
+        };
+
+        let mut state = shared.state.lock().unwrap();
+        state.stats.capture = reader.stats();
+        match &item {
+            Ok(_) => (),
+            Err(e) if !fatal => debug!("{}", e),
+            Err(e) => error!("{} : Stopped reading frames", e),
+        }
+        if state.queue.len() >= queue_size && !fatal {
+            match options.overflow {
+                OverflowPolicy::DropOldest => {
+                    state.queue.pop_front();
+                    state.stats.overflows += 1;
+                }
+                OverflowPolicy::DropNewest => {
+                    state.stats.overflows += 1;
+                    continue;
+                }
+                OverflowPolicy::Block => {
+                    while state.queue.len() >= queue_size && !state.stop {
+                        state = shared.not_full.wait(state).unwrap();
+                    }
+                    if state.stop {
+                        break;
+                    }
+                }
+            }
+        }
+        state.queue.push_back(item);
+        drop(state);
+        shared.not_empty.notify_one();
+        if fatal {
+            break;
+        }
+    }
+    shared.state.lock().unwrap().finished = true;
+    shared.not_empty.notify_all();
+}
+
+impl<'a> Iterator for FrameStream<'a> {
+    type Item = Result<Frame, SVBError>;
+
+    /// The next queued frame, `None` once the thread stopped and the queue is empty.
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(item) = state.queue.pop_front() {
+                drop(state);
+                self.shared.not_full.notify_one();
+                return Some(item);
+            }
+            if state.finished {
+                return None;
+            }
+            state = self.shared.not_empty.wait(state).unwrap();
+        }
+    }
+}
+
+impl<'a> Deref for FrameStream<'a> {
+    type Target = Camera;
+    fn deref(&self) -> &Camera {
+        self.camera
+    }
+}
+
+impl<'a> Drop for FrameStream<'a> {
+    fn drop(&mut self) {
+        self.join();
+        if !self.is_capturing {
+            return;
+        }
+        if let Err(e) = self.camera.stop_video_capture() {
+            error!("{} : Failed to stop video capture on camera_id {}", e, self.camera.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OverflowPolicy, StreamOptions};
+    use crate::camera::Camera;
+    use crate::libsvb::ControlType;
+    use crate::simulator::{SimulatedBackend, SimulatedCameraConfig};
+
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn camera() -> Camera {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            num_stars: 20,
+            ..Default::default()
+        }]));
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        camera.set_roi_format(0, 0, 160, 120, 1).unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1000, 0).unwrap();
+        camera
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let mut camera = camera();
+        let options = StreamOptions {
+            queue_size: 2,
+            overflow: OverflowPolicy::DropOldest,
+        };
+        let mut stream = camera.start_stream(options).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        let first = stream.next().unwrap().unwrap();
+        assert!(first.sequence > 0);
+        assert!(stream.next().unwrap().unwrap().sequence > first.sequence);
+        let stats = stream.stop().unwrap();
+        assert!(stats.overflows > 0);
+        assert!(stats.capture.received > 2);
+
+        // the camera can be reconfigured once the stream is stopped
+        camera.set_roi_format(0, 0, 320, 240, 1).unwrap();
+    }
+
+    #[test]
+    fn test_block() {
+        let mut camera = camera();
+        let options = StreamOptions {
+            queue_size: 2,
+            overflow: OverflowPolicy::Block,
+        };
+        let stream = camera.start_stream(options).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let sequences: Vec<u64> = stream
+            .take(5)
+            .map(|frame| frame.unwrap().sequence)
+            .collect();
+        assert_eq!(sequences, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_stop_during_exposure() {
+        let mut camera = camera();
+        camera.set_ctl_value(ControlType::Exposure, 300_000_000, 0).unwrap();
+        let stream = camera.start_stream(StreamOptions::default()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        drop(stream);
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let stream = camera.start_stream(StreamOptions::default()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        let stats = stream.stop().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!((stats.capture.received, stats.capture.timeouts), (0, 0));
+    }
+}
@@ -0,0 +1,383 @@
+use crate::fits::{self, Pixels};
+use crate::frame::{Frame, ImageMetadata};
+use crate::libsvb::BayerPattern;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::write::ZlibEncoder;
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+mod lz4;
+
+const SIGNATURE: &[u8; 8] = b"XISF0100";
+/// Attached blocks start at a multiple of this, as PixInsight writes them.
+const BLOCK_ALIGNMENT: usize = 4096;
+
+/// Codec of the attached image block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Zlib,
+    Lz4,
+}
+
+/// Value of an XISF property.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    String(String),
+    Int32(i32),
+    Float32(f32),
+    TimePoint(DateTime<Utc>),
+}
+
+impl From<&str> for PropertyValue {
+    fn from(value: &str) -> Self {
+        PropertyValue::String(value.to_string())
+    }
+}
+impl From<String> for PropertyValue {
+    fn from(value: String) -> Self {
+        PropertyValue::String(value)
+    }
+}
+impl From<i32> for PropertyValue {
+    fn from(value: i32) -> Self {
+        PropertyValue::Int32(value)
+    }
+}
+impl From<f32> for PropertyValue {
+    fn from(value: f32) -> Self {
+        PropertyValue::Float32(value)
+    }
+}
+impl From<DateTime<Utc>> for PropertyValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        PropertyValue::TimePoint(value)
+    }
+}
+
+/// A property of the image, like `Instrument:ExposureTime`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    pub id: String,
+    pub value: PropertyValue,
+}
+
+/// An image with the properties and FITS keywords written next to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XisfImage {
+    pub image: fits::Image,
+    pub properties: Vec<Property>,
+    /// Written as `FITSKeyword` elements, for software which only knows FITS.
+    pub keywords: fits::Header,
+    /// Written as the `ColorFilterArray` of single channel images.
+    pub bayer_pattern: Option<BayerPattern>,
+}
+
+impl XisfImage {
+    pub fn new(image: fits::Image) -> Self {
+        Self {
+            image,
+            properties: Vec::new(),
+            keywords: fits::Header::new(),
+            bayer_pattern: None,
+        }
+    }
+
+    /// The frame with the FITS keywords of `fits::Header::for_frame` and the
+    /// matching native properties.
+    pub fn from_frame(frame: &Frame, metadata: &ImageMetadata) -> Self {
+        let mut xisf = Self::new(fits::Image::from_frame(frame));
+        xisf.keywords = fits::Header::for_frame(frame, metadata);
+        xisf.bayer_pattern = frame.bayer_pattern;
+        xisf.set("Observation:Time:Start", frame.exposure_start());
+        xisf.set("Instrument:Camera:Name", metadata.instrument.as_str());
+        xisf.set("Instrument:ExposureTime", (frame.exposure as f64 / 1e6) as f32);
+        // Instrument:Camera:Gain is the conversion gain in e-/ADU, the gain
+        // setting goes in a property of our own namespace
+        xisf.set("SVBONY:Camera:Gain", frame.gain as i32);
+        xisf.set("Instrument:Camera:XBinning", frame.bin());
+        xisf.set("Instrument:Camera:YBinning", frame.bin());
+        if let Some(temperature) = frame.temperature {
+            xisf.set("Instrument:Sensor:Temperature", temperature as f32);
+        }
+        if let Some(temperature) = metadata.target_temperature {
+            xisf.set("Instrument:Sensor:TargetTemperature", temperature as f32);
+        }
+        if let Some(pixel_size) = frame.pixel_size_um(metadata) {
+            // written with the f32 precision the SDK reports it in
+            xisf.set("Instrument:Sensor:XPixelSize", pixel_size as f32);
+            xisf.set("Instrument:Sensor:YPixelSize", pixel_size as f32);
+        }
+        if let Some(geometry) = frame.geometry {
+            // in m
+            xisf.set("Instrument:Telescope:FocalLength", (geometry.focal_length_mm / 1000.0) as f32);
+        }
+        xisf
+    }
+
+    /// Set property `id`, replacing its value if it is already set.
+    pub fn set(&mut self, id: &str, value: impl Into<PropertyValue>) {
+        let property = Property {
+            id: id.to_string(),
+            value: value.into(),
+        };
+        match self.properties.iter_mut().find(|p| p.id == property.id) {
+            Some(existing) => *existing = property,
+            None => self.properties.push(property),
+        }
+    }
+
+    /// A monolithic XISF 1.0 file with the image in an attached block.
+    pub fn to_bytes(&self, compression: Compression) -> Vec<u8> {
+        let (sample_format, item_size, samples, bounds) = samples(&self.image.pixels);
+        let samples_len = samples.len();
+        let (block, codec) = match compression {
+            Compression::None => (samples, None),
+            Compression::Zlib => (zlib(&shuffle(&samples, item_size)), Some("zlib")),
+            Compression::Lz4 => (lz4::compress(&shuffle(&samples, item_size)), Some("lz4")),
+        };
+        let mut attributes = format!(
+            r#"geometry="{}:{}:{}" sampleFormat="{}" colorSpace="{}""#,
+            self.image.width,
+            self.image.height,
+            self.image.channels,
+            sample_format,
+            if self.image.channels == 3 { "RGB" } else { "Gray" },
+        );
+        if let Some((min, max)) = bounds {
+            write!(attributes, r#" bounds="{}:{}""#, min, max).unwrap();
+        }
+        match codec {
+            // shuffled samples, see `shuffle`
+            Some(codec) if item_size > 1 => {
+                write!(attributes, r#" compression="{}+sh:{}:{}""#, codec, samples_len, item_size).unwrap()
+            }
+            Some(codec) => write!(attributes, r#" compression="{}:{}""#, codec, samples_len).unwrap(),
+            None => (),
+        }
+
+        // the position of the block changes the length of the header, which
+        // is settled after a round or two
+        let mut position = BLOCK_ALIGNMENT;
+        let header = loop {
+            let header = self.header(&attributes, position, block.len());
+            let end = (SIGNATURE.len() + 8 + header.len()).next_multiple_of(BLOCK_ALIGNMENT);
+            if end <= position {
+                break header;
+            }
+            position = end;
+        };
+
+        let mut xisf = Vec::with_capacity(position + block.len());
+        xisf.extend_from_slice(SIGNATURE);
+        xisf.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        xisf.extend_from_slice(&[0; 4]);
+        xisf.extend_from_slice(header.as_bytes());
+        xisf.resize(position, 0);
+        xisf.extend_from_slice(&block);
+        xisf
+    }
+
+    fn header(&self, attributes: &str, position: usize, len: usize) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "\n",
+            r#"<xisf version="1.0" xmlns="http://www.pixinsight.com/xisf" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.pixinsight.com/xisf http://pixinsight.com/xisf/xisf-1.0.xsd">"#,
+            "\n",
+        ));
+        writeln!(xml, r#"<Image {} location="attachment:{}:{}">"#, attributes, position, len).unwrap();
+        for property in &self.properties {
+            xml.push_str(&property_element(property));
+        }
+        for card in self.keywords.cards() {
+            let value = card.value.as_ref().map(|v| v.to_string()).unwrap_or_default();
+            writeln!(
+                xml,
+                r#"<FITSKeyword name="{}" value="{}" comment="{}"/>"#,
+                escape(&card.keyword),
+                escape(value.trim()),
+                escape(&card.comment)
+            )
+            .unwrap();
+        }
+        if let (Some(pattern), 1) = (self.bayer_pattern, self.image.channels) {
+            writeln!(xml, r#"<ColorFilterArray pattern="{}" width="2" height="2"/>"#, pattern).unwrap();
+        }
+        xml.push_str("</Image>\n<Metadata>\n");
+        xml.push_str(&property_element(&Property {
+            id: "XISF:CreationTime".to_string(),
+            value: Utc::now().into(),
+        }));
+        xml.push_str(&property_element(&Property {
+            id: "XISF:CreatorApplication".to_string(),
+            value: concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")).into(),
+        }));
+        xml.push_str("</Metadata>\n</xisf>\n");
+        xml
+    }
+}
+
+fn property_element(property: &Property) -> String {
+    let id = escape(&property.id);
+    match &property.value {
+        PropertyValue::String(value) => {
+            format!("<Property id=\"{}\" type=\"String\">{}</Property>\n", id, escape(value))
+        }
+        PropertyValue::Int32(value) => format!("<Property id=\"{}\" type=\"Int32\" value=\"{}\"/>\n", id, value),
+        PropertyValue::Float32(value) => format!("<Property id=\"{}\" type=\"Float32\" value=\"{}\"/>\n", id, value),
+        PropertyValue::TimePoint(value) => format!(
+            "<Property id=\"{}\" type=\"TimePoint\" value=\"{}\"/>\n",
+            id,
+            value.to_rfc3339_opts(SecondsFormat::Millis, true)
+        ),
+    }
+}
+
+/// Escape text and attribute values, which are always in double quotes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Sample format, sample size, little endian samples and the bounds of float
+/// samples. XISF has no signed integers, they become floats.
+fn samples(pixels: &Pixels) -> (&'static str, usize, Vec<u8>, Option<(f64, f64)>) {
+    fn bounds(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+        let (min, max) = values
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| (min.min(v), max.max(v)));
+        match min < max {
+            true => Some((min, max)),
+            false if min.is_finite() => Some((min, min + 1.0)),
+            false => Some((0.0, 1.0)),
+        }
+    }
+    match pixels {
+        Pixels::U8(values) => ("UInt8", 1, values.clone(), None),
+        Pixels::U16(values) => ("UInt16", 2, values.iter().flat_map(|v| v.to_le_bytes()).collect(), None),
+        Pixels::I16(values) => (
+            "Float32",
+            4,
+            values.iter().flat_map(|&v| (v as f32).to_le_bytes()).collect(),
+            bounds(values.iter().map(|&v| v as f64)),
+        ),
+        Pixels::F32(values) => (
+            "Float32",
+            4,
+            values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            bounds(values.iter().map(|&v| v as f64)),
+        ),
+        Pixels::I32(values) => (
+            "Float64",
+            8,
+            values.iter().flat_map(|&v| (v as f64).to_le_bytes()).collect(),
+            bounds(values.iter().map(|&v| v as f64)),
+        ),
+        Pixels::F64(values) => (
+            "Float64",
+            8,
+            values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            bounds(values.iter().copied()),
+        ),
+    }
+}
+
+/// Group the n-th bytes of all samples, which compresses better.
+fn shuffle(samples: &[u8], item_size: usize) -> Vec<u8> {
+    if item_size < 2 {
+        return samples.to_vec();
+    }
+    let count = samples.len() / item_size;
+    let mut shuffled = Vec::with_capacity(samples.len());
+    for byte in 0..item_size {
+        shuffled.extend(samples.chunks_exact(item_size).map(|sample| sample[byte]));
+    }
+    shuffled.extend_from_slice(&samples[count * item_size..]);
+    shuffled
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::with_capacity(data.len() / 2), flate2::Compression::default());
+    // writing to a Vec doesn't fail
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Compression, XisfImage};
+    use crate::camera::Camera;
+    use crate::libsvb::{ControlType, ImageType};
+    use crate::simulator::{SimulatedBackend, SimulatedCameraConfig};
+
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+    use std::sync::Arc;
+
+    /// The XML header and the attached block of an XISF file.
+    fn split(xisf: &[u8]) -> (String, Vec<u8>) {
+        assert_eq!(&xisf[..8], b"XISF0100");
+        let len = u32::from_le_bytes(xisf[8..12].try_into().unwrap()) as usize;
+        let header = String::from_utf8(xisf[16..16 + len].to_vec()).unwrap();
+        let location = header.split("location=\"attachment:").nth(1).unwrap();
+        let mut numbers = location.split(['"', ':']).map(|n| n.parse::<usize>().unwrap());
+        let (position, size) = (numbers.next().unwrap(), numbers.next().unwrap());
+        assert_eq!(position % 4096, 0);
+        assert_eq!(xisf.len(), position + size);
+        (header, xisf[position..].to_vec())
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let backend = Arc::new(SimulatedBackend::new(vec![SimulatedCameraConfig {
+            max_width: 320,
+            max_height: 240,
+            is_color: true,
+            num_stars: 20,
+            ..Default::default()
+        }]));
+        let mut camera = Camera::with_backend(0, backend).init().unwrap();
+        camera.set_ctl_value(ControlType::Exposure, 1500, 0).unwrap();
+        camera.set_img_type(ImageType::Raw16).unwrap();
+        let frame = camera.start_video_capture().unwrap().get_video_frame().unwrap();
+        let mut xisf = XisfImage::from_frame(&frame, &camera.image_metadata().unwrap());
+        xisf.set("Observation:Object:Name", "M 31 <Andromeda>");
+
+        let (header, block) = split(&xisf.to_bytes(Compression::None));
+        assert!(header.contains(r#"geometry="320:240:1" sampleFormat="UInt16" colorSpace="Gray""#));
+        assert!(header.contains(r#"<Property id="Instrument:ExposureTime" type="Float32" value="0.0015"/>"#));
+        assert!(header.contains(r#"<FITSKeyword name="EXPTIME" value="0.0015" comment="[s] exposure time"/>"#));
+        assert!(header.contains(r#"<FITSKeyword name="GAIN""#));
+        assert!(!header.contains("Instrument:Camera:Gain"));
+        assert!(header.contains(&format!(r#"<Property id="SVBONY:Camera:Gain" type="Int32" value="{}"/>"#, frame.gain)));
+        assert!(header.contains(r#"<FITSKeyword name="XPIXSZ" value="4.63" comment="[um] binned pixel width"/>"#));
+        assert!(header.contains(r#"<Property id="Instrument:Sensor:XPixelSize" type="Float32" value="4.63"/>"#));
+        assert!(header.contains(r#"<FITSKeyword name="ROWORDER" value="'TOP-DOWN'""#));
+        assert!(header.contains(r#">M 31 &lt;Andromeda&gt;</Property>"#));
+        let cfa = format!(r#"<ColorFilterArray pattern="{}" width="2" height="2"/>"#, frame.bayer_pattern.unwrap());
+        assert!(header.contains(&cfa));
+        assert_eq!(block, &frame.data[..]);
+
+        let (header, block) = split(&xisf.to_bytes(Compression::Zlib));
+        assert!(header.contains(r#"compression="zlib+sh:153600:2""#));
+        let mut shuffled = Vec::new();
+        ZlibDecoder::new(&block[..]).read_to_end(&mut shuffled).unwrap();
+        // low bytes of all samples, then the high bytes
+        assert_eq!((shuffled[0], shuffled[320 * 240]), (frame.data[0], frame.data[1]));
+
+        let (header, block) = split(&xisf.to_bytes(Compression::Lz4));
+        assert!(header.contains(r#"compression="lz4+sh:153600:2""#));
+        assert!(block.len() < 153600);
+    }
+}
@@ -0,0 +1,115 @@
+//! LZ4 block format compression, the `lz4` codec of XISF.
+
+/// Shortest match the format can encode.
+const MIN_MATCH: usize = 4;
+/// The last 5 bytes are always literals.
+const LAST_LITERALS: usize = 5;
+/// The last match starts at least 12 bytes before the end.
+const MF_LIMIT: usize = 12;
+const MAX_OFFSET: usize = 65535;
+const HASH_BITS: u32 = 12;
+
+fn read_u32(src: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([src[at], src[at + 1], src[at + 2], src[at + 3]])
+}
+
+fn push_len(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+/// Literals followed by a match of `match_len` bytes `offset` back, or just
+/// the final literals without a match.
+fn push_sequence(out: &mut Vec<u8>, literals: &[u8], offset_and_len: Option<(usize, usize)>) {
+    let match_len = offset_and_len.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push(((literals.len().min(15) as u8) << 4) | match_len.min(15) as u8);
+    if literals.len() >= 15 {
+        push_len(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = offset_and_len {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_len >= 15 {
+            push_len(out, match_len - 15);
+        }
+    }
+}
+
+/// Compress `src` into a single LZ4 block with greedy hash matching.
+pub(super) fn compress(src: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(src.len() / 2 + 16);
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut i = 0;
+    while src.len() > MF_LIMIT && i + MF_LIMIT <= src.len() {
+        let sequence = read_u32(src, i);
+        let hash = (sequence.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let candidate = std::mem::replace(&mut table[hash], i);
+        if candidate == usize::MAX || i - candidate > MAX_OFFSET || read_u32(src, candidate) != sequence {
+            i += 1;
+            continue;
+        }
+        let mut len = MIN_MATCH;
+        while i + len < src.len() - LAST_LITERALS && src[candidate + len] == src[i + len] {
+            len += 1;
+        }
+        push_sequence(&mut out, &src[anchor..i], Some((i - candidate, len)));
+        i += len;
+        anchor = i;
+    }
+    push_sequence(&mut out, &src[anchor..], None);
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::compress;
+
+    fn decompress(src: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut i = 0;
+        let len = |i: &mut usize, mut len: usize| {
+            if len == 15 {
+                loop {
+                    let byte = src[*i];
+                    *i += 1;
+                    len += byte as usize;
+                    if byte != 255 {
+                        break;
+                    }
+                }
+            }
+            len
+        };
+        loop {
+            let token = src[i];
+            i += 1;
+            let literals = len(&mut i, (token >> 4) as usize);
+            out.extend_from_slice(&src[i..i + literals]);
+            i += literals;
+            if i == src.len() {
+                return out;
+            }
+            let offset = u16::from_le_bytes([src[i], src[i + 1]]) as usize;
+            i += 2;
+            let match_len = len(&mut i, (token & 15) as usize) + 4;
+            for _ in 0..match_len {
+                out.push(out[out.len() - offset]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lz4() {
+        let repetitive: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8 / 8).collect();
+        let noisy: Vec<u8> = (0..5000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+        for src in [vec![], b"short".to_vec(), vec![0; 1000], repetitive, noisy] {
+            let compressed = compress(&src);
+            assert_eq!(decompress(&compressed), src);
+        }
+        assert!(compress(&[0; 1000]).len() < 20);
+    }
+}